@@ -3,11 +3,11 @@ use std::fmt::{Debug, Display, Write};
 use std::fs::File;
 use std::io::BufReader;
 use std::io::Write as ioWrite;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
 use chrono::{DateTime, Local};
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use log::{debug, info, warn, LevelFilter};
 use mktemp::Temp;
 use secstr::SecUtf8;
@@ -15,10 +15,14 @@ use serde::Deserialize;
 use serde::Serialize;
 use serde_with::{DisplayFromStr, PickFirst};
 use simplelog::{ColorChoice, Config, TermLogger, TerminalMode};
-use utils::cmd::{run_cmd, run_cmd_inherit, run_cmd_piped};
-use utils::folder::BackupGroup;
+use utils::cmd::{run_argv, run_argv_inherit, run_cmd, run_cmd_piped, run_cmd_streaming};
+use utils::folder::{filter_by_tags, BackupGroup};
+use utils::progress::{BackupProgress, ProgressReporter};
+use utils::signals::SHUTDOWN_REQUESTED;
 use void::Void;
 
+mod migrations;
+mod restore;
 mod sources;
 mod utils;
 
@@ -43,6 +47,23 @@ impl PruneSettings {
     }
 }
 
+/// Scope/options for `Borg::check`, modeled on zvault's `CheckOptions`.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+struct CheckSettings {
+    /// Check the repository structure *and* every archive in it.
+    #[serde(default)]
+    all_backups: bool,
+    /// Only check archives matching this prefix (`--glob-archives '<prefix>*'`).
+    single_backup: Option<String>,
+    /// Verify archived data against its stored checksums, not just metadata.
+    #[serde(default)]
+    verify_data: bool,
+    /// Let borg rewrite the repository to fix what it finds. Off by
+    /// default - this is destructive and should be an explicit opt-in.
+    #[serde(default)]
+    repair: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 struct PasswordStore {
     system: String,
@@ -108,6 +129,9 @@ struct ConditionalExclude {
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct Repository {
     path: String,
+    /// Short alias for `--repo` selection, e.g. "offsite". Falls back to
+    /// matching against `path` as a substring when no repo has this alias.
+    name: Option<String>,
     #[serde(default)]
     tags: Vec<String>,
 
@@ -147,50 +171,148 @@ impl Repository {
         output.status.success()
     }
 
+    /// Abort (with a warning) if the repo's filesystem has less free space
+    /// than its configured `min_free`. No limit configured always passes.
+    fn check_free_space(&self) -> bool {
+        let Some(min_free) = self.options.min_free else {
+            return true;
+        };
+        match fs4::available_space(&self.path) {
+            Ok(available) => {
+                let available = byte_unit::Byte::from_u64(available);
+                if available < min_free {
+                    warn!(
+                        "Not enough free space on {}: {} available, {} required",
+                        self.path,
+                        available.get_appropriate_unit(byte_unit::UnitType::Binary),
+                        min_free.get_appropriate_unit(byte_unit::UnitType::Binary)
+                    );
+                    return false;
+                }
+                true
+            }
+            Err(e) => {
+                warn!("Failed to check free space on {}: {}", self.path, e);
+                true
+            }
+        }
+    }
+
     fn backup_create(
         &self,
         backup_source_groups: &[BackupGroup],
         excludes: &[String],
         date: &DateTime<Local>,
+        dry_run: bool,
+        reporter: &dyn ProgressReporter,
+        tags: &[String],
+        exclude_tags: &[String],
     ) {
         self.export_password();
         if self.is_valid() {
+            if !self.check_free_space() {
+                return;
+            }
             info!("Processing {}", self.path);
             for backup_source in backup_source_groups {
+                if SHUTDOWN_REQUESTED.load(std::sync::atomic::Ordering::SeqCst) {
+                    warn!("Shutdown requested, not starting source {}", backup_source.name);
+                    break;
+                }
                 info!("Processing source {}", backup_source.name);
-                let mut folders = backup_source.r#type.get_folders();
+                let mut folders = match backup_source.r#type.get_folders() {
+                    Ok(folders) => folders,
+                    Err(e) => {
+                        warn!("Skipping source {}: {}", backup_source.name, e);
+                        continue;
+                    }
+                };
                 if !self.tags.is_empty() {
                     folders.retain(|f| self.tags.iter().any(|item| f.tags.contains(item)));
                 }
-                if backup_source.r#type.pre_backup() {
-                    let paths: Vec<PathBuf> = folders.iter().map(|f| f.folder.get_path()).collect();
-                    info!("Backing up folders {:?}", paths);
-                    // Create Backup
-                    if !folders.is_empty() {
-                        Borg::_backup_create(
-                            &format!(
-                                "{} {}",
-                                backup_source.r#type.get_additional_options(),
-                                &self.options.cmdline.clone().unwrap_or_default()
-                            ),
-                            &self.path,
-                            &format!(
-                                "{}-{}",
-                                backup_source.r#type.get_hostname(),
-                                date.to_rfc3339()
-                            ),
-                            &paths,
-                            excludes,
-                        )
-                    }
+                filter_by_tags(&mut folders, tags, exclude_tags);
+                if let Err(e) = backup_source.r#type.pre_backup() {
+                    warn!("Skipping source {}: {}", backup_source.name, e);
+                    continue;
+                }
+                let paths: Vec<PathBuf> = folders.iter().map(|f| f.folder.get_path()).collect();
+                info!("Backing up folders {:?}", paths);
+                // Create Backup
+                if !folders.is_empty() {
+                    let folder_sizes: Vec<(String, u64)> = folders
+                        .iter()
+                        .map(|f| {
+                            let path = f.folder.get_path().to_string_lossy().into_owned();
+                            (path, f.folder.get_size().unwrap_or(0))
+                        })
+                        .collect();
+                    Borg::_backup_create(
+                        &format!(
+                            "{} {}",
+                            backup_source.r#type.get_additional_options(),
+                            &self.options.cmdline.clone().unwrap_or_default()
+                        ),
+                        &self.path,
+                        &format!(
+                            "{}-{}",
+                            backup_source.r#type.get_hostname(),
+                            date.to_rfc3339()
+                        ),
+                        &paths,
+                        excludes,
+                        dry_run,
+                        &folder_sizes,
+                        reporter,
+                    )
+                }
+                if let Err(e) = backup_source.r#type.post_backup() {
+                    warn!("post_backup for {} failed: {}", backup_source.name, e);
                 }
-                backup_source.r#type.post_backup();
             }
         } else {
             warn!("Skipping repo {}", self.path);
         }
     }
 
+    /// Run `borg check` against this repo for the given `settings` and
+    /// report whether it passed. Mirrors `is_valid`/`backup_prune` in
+    /// shape: export the password, bail out on an invalid repo, stream
+    /// borg's own progress output straight to the terminal.
+    fn check(&self, settings: &CheckSettings) -> bool {
+        self.export_password();
+        if !self.is_valid() {
+            warn!("Skipping repo {}", self.path);
+            return false;
+        }
+
+        let mut args: Vec<String> = vec!["check".to_string()];
+        if let Some(prefix) = &settings.single_backup {
+            args.push("--archives-only".to_string());
+            args.push("--glob-archives".to_string());
+            args.push(format!("{prefix}*"));
+        } else if !settings.all_backups {
+            // Neither an explicit archive nor "all archives" was requested,
+            // so just validate the repository structure itself.
+            args.push("--repository-only".to_string());
+        }
+        if settings.verify_data {
+            args.push("--verify-data".to_string());
+        }
+        if settings.repair {
+            warn!(
+                "Running borg check --repair on {} - this rewrites the repository",
+                self.path
+            );
+            args.push("--repair".to_string());
+        }
+        args.push(self.path.clone());
+
+        let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+        run_argv_inherit("borg", &arg_refs, None)
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
     fn backup_prune(&self, backup_groups: &[BackupGroup]) {
         let prefixes: Vec<String> = backup_groups
             .iter()
@@ -198,15 +320,22 @@ impl Repository {
             .collect();
         prefixes.iter().for_each(|prefix| {
             if self.is_valid() {
-                //let mut keep_vec = vec![];
                 let prune_options = self.options.prune.clone().unwrap_or_default();
-                let cmd = format!("borg prune --list --stats -v --keep-daily={} --keep-weekly={} --keep-monthly={} --keep-yearly={} --glob-archives '{prefix}*' {}",
-                                  prune_options.daily.unwrap_or_default(),
-                                  prune_options.weekly.unwrap_or_default(),
-                                  prune_options.monthly.unwrap_or_default(),
-                                  prune_options.yearly.unwrap_or_default(), self.path
-                                 );
-                run_cmd_piped(&cmd);
+                let args = [
+                    "prune".to_string(),
+                    "--list".to_string(),
+                    "--stats".to_string(),
+                    "-v".to_string(),
+                    format!("--keep-daily={}", prune_options.daily.unwrap_or_default()),
+                    format!("--keep-weekly={}", prune_options.weekly.unwrap_or_default()),
+                    format!("--keep-monthly={}", prune_options.monthly.unwrap_or_default()),
+                    format!("--keep-yearly={}", prune_options.yearly.unwrap_or_default()),
+                    "--glob-archives".to_string(),
+                    format!("{prefix}*"),
+                    self.path.clone(),
+                ];
+                let arg_refs: Vec<&str> = args.iter().map(String::as_str).collect();
+                let _ = run_argv_inherit("borg", &arg_refs, None);
             }
         });
     }
@@ -237,6 +366,12 @@ struct RepositoryOptions {
     prune: Option<PruneSettings>,
     password: Option<PasswordOptions>,
     cmdline: Option<String>,
+    /// Minimum free space required on the repo's filesystem before a
+    /// backup is attempted; the repo is skipped (with a warning) if the
+    /// pre-flight check comes in under this.
+    // Requires byte-unit's `serde` feature enabled in Cargo.toml, or the
+    // `Deserialize` derive above fails to compile.
+    min_free: Option<byte_unit::Byte>,
 }
 
 impl RepositoryOptions {
@@ -253,6 +388,7 @@ impl RepositoryOptions {
             prune,
             password: self.password.clone().or(parent.password.clone()),
             cmdline: self.cmdline.clone().or(parent.cmdline.clone()),
+            min_free: self.min_free.or(parent.min_free),
         }
     }
 }
@@ -260,6 +396,11 @@ impl RepositoryOptions {
 #[serde_with::serde_as]
 #[derive(Serialize, Deserialize, Debug, Default)]
 struct Borg {
+    /// Schema version of this config document. Missing (older configs)
+    /// counts as 0; `borg-helper config upgrade` migrates it forward to
+    /// [`migrations::CURRENT_VERSION`].
+    #[serde(default)]
+    version: u32,
     repository: Repositories,
     backups: Vec<BackupGroup>,
 
@@ -297,21 +438,90 @@ impl Borg {
         }
     }
 
-    fn backup_create(&self) {
-        for repo in &self.repository.repositories {
-            repo.backup_create(&self.backups, &self.excludes, &self.date);
+    /// Repositories matching `selector`: by alias first, falling back to a
+    /// substring match against `path`. `None` selects every repository.
+    fn select_repositories(&self, selector: Option<&str>) -> Vec<&Repository> {
+        let Some(selector) = selector else {
+            return self.repository.repositories.iter().collect();
+        };
+        let by_alias: Vec<&Repository> = self
+            .repository
+            .repositories
+            .iter()
+            .filter(|r| r.name.as_deref() == Some(selector))
+            .collect();
+        if !by_alias.is_empty() {
+            return by_alias;
+        }
+        self.repository
+            .repositories
+            .iter()
+            .filter(|r| r.path.contains(selector))
+            .collect()
+    }
+
+    fn backup_create(
+        &self,
+        repo_selector: Option<&str>,
+        dry_run: bool,
+        reporter: &dyn ProgressReporter,
+        tags: &[String],
+        exclude_tags: &[String],
+    ) {
+        for repo in self.select_repositories(repo_selector) {
+            repo.backup_create(
+                &self.backups,
+                &self.excludes,
+                &self.date,
+                dry_run,
+                reporter,
+                tags,
+                exclude_tags,
+            );
+        }
+    }
+
+    /// Sum each folder's `get_size` into every tag it carries, across all
+    /// configured backup sources - the `--by-tag` view for `list`.
+    fn sizes_by_tag(&self) -> TagSizes {
+        let mut sizes = TagSizes::default();
+        for backup_source in &self.backups {
+            let folders = match backup_source.r#type.get_folders() {
+                Ok(folders) => folders,
+                Err(e) => {
+                    warn!("Skipping source {}: {}", backup_source.name, e);
+                    continue;
+                }
+            };
+            if let Err(e) = backup_source.r#type.pre_backup() {
+                warn!("Skipping source {}: {}", backup_source.name, e);
+                continue;
+            }
+            for folder_entry in &folders {
+                let size = folder_entry.folder.get_size().unwrap_or_default() as usize;
+                if folder_entry.tags.is_empty() {
+                    sizes.add("untagged", size);
+                } else {
+                    for tag in &folder_entry.tags {
+                        sizes.add(tag, size);
+                    }
+                }
+            }
+            if let Err(e) = backup_source.r#type.post_backup() {
+                warn!("post_backup for {} failed: {}", backup_source.name, e);
+            }
         }
+        sizes
     }
 
-    fn backup_prune(&self) {
-        self.repository.repositories.iter().for_each(|repo| {
+    fn backup_prune(&self, repo_selector: Option<&str>) {
+        for repo in self.select_repositories(repo_selector) {
             repo.backup_prune(&self.backups);
-        });
+        }
     }
 
-    #[allow(dead_code)]
-    fn run_every_repo(&self, command: &str) {
-        for repo in &self.repository.repositories {
+    fn run_every_repo(&self, command: &str, repo_selector: Option<&str>) {
+        for repo in self.select_repositories(repo_selector) {
             if repo.is_valid() {
                 let cmd = format!("borg {} {}", command, repo.path);
                 run_cmd_piped(&cmd);
@@ -319,9 +529,152 @@ impl Borg {
         }
     }
 
-    #[allow(dead_code)]
-    fn compact(&self) {
-        self.run_every_repo("compact");
+    fn compact(&self, repo_selector: Option<&str>) {
+        self.run_every_repo("compact", repo_selector);
+    }
+
+    fn check(&self, settings: &CheckSettings, repo_selector: Option<&str>) -> CheckReport {
+        let mut report = CheckReport::default();
+        for repo in self.select_repositories(repo_selector) {
+            let passed = repo.check(settings);
+            report.add_result(&repo.path, passed);
+        }
+        report
+    }
+
+    fn find_repo(&self, repo_selector: &str) -> Option<&Repository> {
+        self.select_repositories(Some(repo_selector)).into_iter().next()
+    }
+
+    /// Resolve `archive_prefix` (e.g. a hostname, as used by
+    /// `_backup_create`) to the most recent matching archive name, so
+    /// callers don't have to spell out the full `<hostname>-<rfc3339>`
+    /// archive name. Falls back to treating `archive_prefix` as an exact
+    /// archive name if nothing matches.
+    fn resolve_archive(repo: &Repository, archive_prefix: &str) -> String {
+        let glob = format!("{archive_prefix}*");
+        let resolved = run_argv(
+            "borg",
+            &["list", "--last", "1", "--glob-archives", &glob, "--format", "{archive}{NL}", &repo.path],
+        )
+        .ok()
+        .and_then(|output| {
+            String::from_utf8_lossy(&output.stdout).lines().next().map(str::trim).map(str::to_string)
+        })
+        .filter(|s| !s.is_empty());
+        resolved.unwrap_or_else(|| archive_prefix.to_string())
+    }
+
+    /// Extract `paths` (or the whole archive, if empty) from `archive` in
+    /// `repo_path` into `target_path`.
+    fn restore(&self, repo_path: &str, archive: &str, target_path: &str, paths: &[PathBuf]) -> bool {
+        let Some(repo) = self.find_repo(repo_path) else {
+            warn!("Unknown repository {}", repo_path);
+            return false;
+        };
+        if !repo.is_valid() {
+            warn!("Skipping repo {}", repo.path);
+            return false;
+        }
+        if let Err(e) = std::fs::create_dir_all(target_path) {
+            warn!("Failed to create restore target {}: {}", target_path, e);
+            return false;
+        }
+        let archive_name = Self::resolve_archive(repo, archive);
+        let archive_spec = format!("{}::{archive_name}", repo.path);
+        let mut args = vec!["extract", &archive_spec];
+        let path_strs: Vec<&str> = paths.iter().filter_map(|p| p.to_str()).collect();
+        args.extend(path_strs.iter().copied());
+        // `borg extract` writes relative to the current working directory.
+        run_argv_inherit("borg", &args, Some(Path::new(target_path)))
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+
+    /// FUSE-mount `archive` from `repo_path` at `mountpoint`. Pair with
+    /// [`Borg::umount`] to tear it back down.
+    fn mount(&self, repo_path: &str, archive: &str, mountpoint: &str) -> bool {
+        let Some(repo) = self.find_repo(repo_path) else {
+            warn!("Unknown repository {}", repo_path);
+            return false;
+        };
+        if !repo.is_valid() {
+            warn!("Skipping repo {}", repo.path);
+            return false;
+        }
+        if let Err(e) = std::fs::create_dir_all(mountpoint) {
+            warn!("Failed to create mountpoint {}: {}", mountpoint, e);
+            return false;
+        }
+        let archive_name = Self::resolve_archive(repo, archive);
+        let archive_spec = format!("{}::{archive_name}", repo.path);
+        run_argv("borg", &["mount", &archive_spec, mountpoint])
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    fn umount(mountpoint: &str) -> bool {
+        run_argv("fusermount", &["-u", mountpoint]).map(|output| output.status.success()).unwrap_or(false)
+    }
+
+    /// Build a catalog of `archive` from `repo_path` and drop the user
+    /// into an interactive shell over it, so individual files can be
+    /// found and cherry-picked without keeping a FUSE mount alive.
+    fn browse(&self, repo_path: &str, archive: &str, mountpoint: &str) -> bool {
+        let Some(repo) = self.find_repo(repo_path) else {
+            warn!("Unknown repository {}", repo_path);
+            return false;
+        };
+        if !repo.is_valid() {
+            warn!("Skipping repo {}", repo.path);
+            return false;
+        }
+        let archive_name = Self::resolve_archive(repo, archive);
+        let Some(catalog) = restore::Catalog::build(&repo.path, &archive_name) else {
+            return false;
+        };
+        let target = restore::RestoreTarget::new(&repo.path, &archive_name, mountpoint);
+        restore::CatalogShell::new(catalog, target).run();
+        true
+    }
+
+    /// Pick the `--tar-filter` borg needs for `out_path`'s extension, if any.
+    fn tar_filter_for(out_path: &str) -> Option<&'static str> {
+        if out_path.ends_with(".tar.gz") || out_path.ends_with(".tgz") {
+            Some("gzip")
+        } else if out_path.ends_with(".tar.zst") {
+            Some("zstd")
+        } else {
+            None
+        }
+    }
+
+    /// Export `paths` (or the whole archive, if empty) from `archive` in
+    /// `repo_path` as a tar stream at `out_path` (`-` for stdout), picking
+    /// the `--tar-filter` implied by `out_path`'s extension.
+    fn export_tar(&self, repo_path: &str, archive: &str, out_path: &str, paths: &[PathBuf]) -> bool {
+        let Some(repo) = self.find_repo(repo_path) else {
+            warn!("Unknown repository {}", repo_path);
+            return false;
+        };
+        if !repo.is_valid() {
+            warn!("Skipping repo {}", repo.path);
+            return false;
+        }
+        let archive_name = Self::resolve_archive(repo, archive);
+        let archive_spec = format!("{}::{archive_name}", repo.path);
+        let filter_arg = Self::tar_filter_for(out_path).map(|filter| format!("--tar-filter={filter}"));
+        let mut args = vec!["export-tar"];
+        if let Some(filter_arg) = &filter_arg {
+            args.push(filter_arg);
+        }
+        args.push(&archive_spec);
+        args.push(out_path);
+        let path_strs: Vec<&str> = paths.iter().filter_map(|p| p.to_str()).collect();
+        args.extend(path_strs.iter().copied());
+        run_argv_inherit("borg", &args, None)
+            .map(|status| status.success())
+            .unwrap_or(false)
     }
 
     fn _backup_create(
@@ -330,6 +683,9 @@ impl Borg {
         name: &str,
         folders: &[PathBuf],
         excludes: &[String],
+        dry_run: bool,
+        folder_sizes: &[(String, u64)],
+        reporter: &dyn ProgressReporter,
     ) {
         let folder_vec_str: Vec<String> = folders
             .iter()
@@ -347,28 +703,44 @@ impl Borg {
         f.write_all(folders_str.as_bytes()).unwrap();
         drop(f);
 
-        let cmd = format!("borg create {options} {repo}::{name} {folder_exclude_str} --exclude-if-present .nobackup --exclude-if-present CACHEDIR.TAG --patterns-from {}", folder_file.to_str().unwrap());
-        run_cmd_inherit(&cmd);
+        let dry_run_flag = if dry_run { " --dry-run" } else { "" };
+        let cmd = format!("borg create{dry_run_flag} --progress --log-json {options} {repo}::{name} {folder_exclude_str} --exclude-if-present .nobackup --exclude-if-present CACHEDIR.TAG --patterns-from {}", folder_file.to_str().unwrap());
+
+        let mut progress = BackupProgress::new(folder_sizes);
+        run_cmd_streaming(&cmd, |line| {
+            if let Some(processed_bytes) = progress.observe_line(line) {
+                reporter.update(processed_bytes, progress.planned_bytes(), progress.elapsed());
+            }
+        });
+        reporter.finish(&progress.folder_timings());
     }
 
     fn get_sizes(&self) -> BackupSize {
         let mut sizes = BackupSize::default();
         for backup_source in &self.backups {
-            let folders = backup_source.r#type.get_folders();
-            // TODO: fix multiple mount calls. Fix auto mount stuff
-            for folder_entry in folders {
-                let skip_folder = folder_entry.options.unwrap_or_default().skip_size;
-                if !skip_folder {
-                    let size = folder_entry.folder.get_size().unwrap_or_default();
-                    sizes.add_size(
-                        &backup_source.name,
-                        folder_entry.folder.get_path().to_str().unwrap(),
-                        size as usize,
-                    );
+            let folders = match backup_source.r#type.get_folders() {
+                Ok(folders) => folders,
+                Err(e) => {
+                    warn!("Skipping source {}: {}", backup_source.name, e);
+                    continue;
                 }
+            };
+            if let Err(e) = backup_source.r#type.pre_backup() {
+                warn!("Skipping source {}: {}", backup_source.name, e);
+                continue;
+            }
+            for folder_entry in folders {
+                let size = folder_entry.folder.get_size().unwrap_or_default();
+                sizes.add_size(
+                    &backup_source.name,
+                    folder_entry.folder.get_path().to_str().unwrap(),
+                    size as usize,
+                );
             }
 
-            backup_source.r#type.post_backup();
+            if let Err(e) = backup_source.r#type.post_backup() {
+                warn!("post_backup for {} failed: {}", backup_source.name, e);
+            }
         }
         sizes
     }
@@ -412,14 +784,182 @@ impl Display for BackupSize {
     }
 }
 
+#[derive(Debug, Default, Clone)]
+struct TagSizes {
+    pub sizes: HashMap<String, usize>,
+}
+
+impl TagSizes {
+    fn add(&mut self, tag: &str, size: usize) {
+        *self.sizes.entry(tag.to_string()).or_default() += size;
+    }
+}
+
+impl Display for TagSizes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (tag, size) in &self.sizes {
+            let size_str = byte_unit::Byte::from_u64(*size as u64)
+                .get_appropriate_unit(byte_unit::UnitType::Binary);
+            writeln!(f, "{}: {}", tag, size_str)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+struct CheckReport {
+    pub results: HashMap<String, bool>,
+}
+
+impl CheckReport {
+    fn add_result(&mut self, repo: &str, passed: bool) {
+        self.results.insert(repo.to_string(), passed);
+    }
+
+    #[allow(dead_code)]
+    fn all_passed(&self) -> bool {
+        self.results.values().all(|passed| *passed)
+    }
+}
+
+impl Display for CheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (repo, passed) in &self.results {
+            writeln!(f, "Repo \"{}\": {}", repo, if *passed { "OK" } else { "FAILED" })?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new backup for every configured source, in every repository
+    Backup {
+        /// Preview what would be backed up without actually creating an archive
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Prune old archives according to the configured retention policy
+    Prune,
+    /// Validate repository/archive integrity with `borg check`
+    Check {
+        /// Check every archive, not just the repository structure
+        #[arg(long)]
+        all: bool,
+        /// Only check archives whose name starts with this prefix
+        #[arg(long)]
+        single: Option<String>,
+        /// Verify archived data against its stored checksums
+        #[arg(long)]
+        verify_data: bool,
+        /// Let borg rewrite the repository to fix what it finds (destructive)
+        #[arg(long)]
+        repair: bool,
+    },
+    /// Compact repositories to reclaim space freed by deleted archives
+    Compact,
+    /// List the archives in every repository
+    List {
+        /// Group configured folders (and their sizes) by tag instead
+        #[arg(long)]
+        by_tag: bool,
+    },
+    /// Show `borg info` for every repository
+    Info,
+    /// Print the size of every configured backup source
+    Size,
+    /// Extract an archive (or specific paths from it) to a directory
+    Restore {
+        /// Path of the repository to restore from
+        repo: String,
+        /// Archive name, or a hostname prefix to restore its latest archive
+        archive: String,
+        /// Directory to extract into
+        target: String,
+        /// Specific paths to extract; extracts the whole archive if empty
+        paths: Vec<PathBuf>,
+    },
+    /// FUSE-mount an archive for browsing
+    Mount {
+        /// Path of the repository to mount from
+        repo: String,
+        /// Archive name, or a hostname prefix to mount its latest archive
+        archive: String,
+        /// Directory to mount the archive at
+        mountpoint: String,
+    },
+    /// Unmount a previously FUSE-mounted archive
+    Umount {
+        /// Mountpoint previously passed to `mount`
+        mountpoint: String,
+    },
+    /// Browse an archive's catalog in an interactive ls/cd/get/stat/find
+    /// shell, extracting individual files without a full restore
+    Shell {
+        /// Path of the repository to browse
+        repo: String,
+        /// Archive name, or a hostname prefix to browse its latest archive
+        archive: String,
+        /// Mountpoint `get` extracts files into
+        mountpoint: String,
+    },
+    /// Export an archive (or specific paths from it) as a tar stream
+    ExportTar {
+        /// Path of the repository to export from
+        repo: String,
+        /// Archive name, or a hostname prefix to export its latest archive
+        archive: String,
+        /// Output path; `.tar.gz`/`.tar.zst` picks the matching compression,
+        /// `-` writes to stdout
+        out: String,
+        /// Specific paths to export; exports the whole archive if empty
+        paths: Vec<PathBuf>,
+    },
+    /// Inspect or migrate the config document's schema
+    Config {
+        #[command(subcommand)]
+        action: ConfigCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigCommand {
+    /// Migrate the config file to the current schema version, in place
+    Upgrade {
+        /// Report which migrations would run without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
-    #[arg(short, long)]
-    show_size: bool,
-
-    #[arg(short, long, default_value = "config.yaml")]
+    #[arg(short, long, default_value = "config.yaml", global = true)]
     config: String,
+
+    /// Only act on the repository matching this alias or path substring.
+    /// Applies to backup/prune/check/compact/list/info; omit it to act on
+    /// every configured repository.
+    #[arg(short, long, global = true)]
+    repo: Option<String>,
+
+    /// Emit machine-readable JSON instead of the live terminal progress bar
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Only back up folders carrying at least one of these tags.
+    /// Comma-separated; empty means every folder. Applies to `backup`.
+    #[arg(long, global = true, value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// Skip folders carrying any of these tags, applied after `--tags`.
+    /// Comma-separated. Applies to `backup`.
+    #[arg(long, global = true, value_delimiter = ',')]
+    exclude_tags: Vec<String>,
+
+    #[command(subcommand)]
+    command: Command,
 }
 
 fn main() {
@@ -430,15 +970,131 @@ fn main() {
         ColorChoice::Auto,
     )
     .unwrap();
+    utils::signals::install();
     let cli = Cli::parse();
+    let repo_selector = cli.repo.as_deref();
+    let reporter = utils::progress::reporter_for(cli.json);
+
+    // `config upgrade` has to run before the config is deserialized into
+    // the current typed schema below - that's exactly what fails on an
+    // old config still using a retired `type:` (`psql`, `mysql`, ...), so
+    // it never reaches the migration code that would fix it. Handle it
+    // against the raw `Value` first, without ever constructing `Borg`.
+    if matches!(cli.command, Command::Config { .. }) {
+        let Command::Config { action } = cli.command else {
+            unreachable!()
+        };
+        match action {
+            ConfigCommand::Upgrade { dry_run } => {
+                let conf_reader = BufReader::new(File::open(&cli.config).unwrap());
+                let mut doc: serde_yaml::Value = serde_yaml::from_reader(conf_reader).unwrap();
+                let report = migrations::upgrade(&mut doc);
+                if report.ran.is_empty() {
+                    println!("Config already at version {}", report.to_version);
+                } else {
+                    println!(
+                        "Migrated config from version {} to {}:",
+                        report.from_version, report.to_version
+                    );
+                    for migration in &report.ran {
+                        println!("  {migration}");
+                    }
+                    if dry_run {
+                        println!("Dry run, not writing {}", cli.config);
+                    } else {
+                        let mut f = File::create(&cli.config).unwrap();
+                        f.write_all(serde_yaml::to_string(&doc).unwrap().as_bytes()).unwrap();
+                        println!("Wrote {}", cli.config);
+                    }
+                }
+            }
+        }
+        return;
+    }
+
     let borg = Borg::from_file(&cli.config);
     debug!("{:?}", borg);
-    if cli.show_size {
-        let sizes = borg.get_sizes();
-        println!("{}", sizes);
-    } else {
-        borg.backup_create();
-        borg.backup_prune();
+    match cli.command {
+        Command::Backup { dry_run } => borg.backup_create(
+            repo_selector,
+            dry_run,
+            reporter.as_ref(),
+            &cli.tags,
+            &cli.exclude_tags,
+        ),
+        Command::Prune => borg.backup_prune(repo_selector),
+        Command::Check {
+            all,
+            single,
+            verify_data,
+            repair,
+        } => {
+            let settings = CheckSettings {
+                all_backups: all,
+                single_backup: single,
+                verify_data,
+                repair,
+            };
+            let report = borg.check(&settings, repo_selector);
+            println!("{}", report);
+        }
+        Command::Compact => borg.compact(repo_selector),
+        Command::List { by_tag } => {
+            if by_tag {
+                println!("{}", borg.sizes_by_tag());
+            } else {
+                borg.run_every_repo("list", repo_selector)
+            }
+        }
+        Command::Info => borg.run_every_repo("info", repo_selector),
+        Command::Size => {
+            let sizes = borg.get_sizes();
+            println!("{}", sizes);
+        }
+        Command::Restore {
+            repo,
+            archive,
+            target,
+            paths,
+        } => {
+            if !borg.restore(&repo, &archive, &target, &paths) {
+                warn!("Restore of {archive} from {repo} failed");
+            }
+        }
+        Command::Mount {
+            repo,
+            archive,
+            mountpoint,
+        } => {
+            if !borg.mount(&repo, &archive, &mountpoint) {
+                warn!("Mounting {archive} from {repo} failed");
+            }
+        }
+        Command::Umount { mountpoint } => {
+            if !Borg::umount(&mountpoint) {
+                warn!("Unmounting {mountpoint} failed");
+            }
+        }
+        Command::Shell {
+            repo,
+            archive,
+            mountpoint,
+        } => {
+            if !borg.browse(&repo, &archive, &mountpoint) {
+                warn!("Browsing {archive} from {repo} failed");
+            }
+        }
+        Command::ExportTar {
+            repo,
+            archive,
+            out,
+            paths,
+        } => {
+            if !borg.export_tar(&repo, &archive, &out, &paths) {
+                warn!("Exporting {archive} from {repo} failed");
+            }
+        }
+        Command::Config { .. } => unreachable!("handled above, before Borg::from_file"),
     }
 }
 
@@ -525,6 +1181,7 @@ mod test {
             Box::new(SSHBackup {
                 folders,
                 target: "localhost".to_string(),
+                ..Default::default()
             }),
             repo_individual_files,
         );
@@ -570,7 +1227,7 @@ mod test {
             ..Default::default()
         };
 
-        borg.backup_create();
+        borg.backup_create(None, false, crate::utils::progress::reporter_for(false).as_ref(), &[], &[]);
 
         let mount_path = Temp::new_dir().unwrap();
         let _output = run_cmd(&format!(
@@ -628,7 +1285,7 @@ mod test {
 
         // LOCAL
         assert_eq!(borg.backups[1].r#type.get_additional_options().len(), 0);
-        let local_folders = borg.backups[1].r#type.get_folders();
+        let local_folders = borg.backups[1].r#type.get_folders().unwrap();
 
         assert_eq!(local_folders.len(), 2);
 
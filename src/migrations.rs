@@ -0,0 +1,170 @@
+use serde_yaml::Value;
+
+/// Schema version written by this build. Bump this and add a `Migration`
+/// below whenever a `BackupType`/`BackupGroup` shape changes in a way that
+/// would silently misparse an older config.
+pub const CURRENT_VERSION: u32 = 1;
+
+/// One step in the migration chain, applied to the raw YAML document
+/// rather than a typed struct so it keeps working even once the current
+/// `Borg`/`Repository`/... structs have moved past the shape it targets.
+struct Migration {
+    from_version: u32,
+    description: &'static str,
+    apply: fn(&mut Value),
+}
+
+/// Migrate a legacy single-engine source (`type: psql`/`type: mysql`, one
+/// monolithic dump) to the generalized, per-database `DatabaseBackup`
+/// (`type: database`), which is the only source that still knows how to
+/// dump either engine.
+fn migrate_legacy_engine_to_database(doc: &mut Value) {
+    let Some(Value::Sequence(backups)) = doc.get_mut("backups") else {
+        return;
+    };
+    for backup in backups {
+        let Value::Mapping(backup) = backup else { continue };
+        let engine = match backup.get("type") {
+            Some(Value::String(t)) if t == "psql" => "postgres",
+            Some(Value::String(t)) if t == "mysql" => "mysql",
+            _ => continue,
+        };
+        backup.insert(Value::String("type".to_string()), Value::String("database".to_string()));
+        backup.insert(Value::String("engine".to_string()), Value::String(engine.to_string()));
+    }
+}
+
+fn migrations() -> Vec<Migration> {
+    vec![Migration {
+        from_version: 0,
+        description: "stamp the config with an explicit schema version, and migrate `type: psql`/`type: mysql` sources to `type: database`/`engine: postgres|mysql`",
+        apply: |doc| {
+            migrate_legacy_engine_to_database(doc);
+            if let Value::Mapping(map) = doc {
+                map.insert(Value::String("version".to_string()), Value::Number(1.into()));
+            }
+        },
+    }]
+}
+
+fn config_version(doc: &Value) -> u32 {
+    doc.get("version")
+        .and_then(Value::as_u64)
+        .map(|v| v as u32)
+        .unwrap_or(0)
+}
+
+/// Which migrations ran, and the version the document started/ended at.
+pub struct MigrationReport {
+    pub from_version: u32,
+    pub to_version: u32,
+    pub ran: Vec<String>,
+}
+
+impl MigrationReport {
+    pub fn changed(&self) -> bool {
+        self.from_version != self.to_version
+    }
+}
+
+/// Walk `doc` forward one version at a time until it reaches
+/// [`CURRENT_VERSION`] (or no further migration applies), mutating it in
+/// place. Does not write anything to disk - that's left to the caller, so
+/// a dry run can report what *would* change without touching the file.
+pub fn upgrade(doc: &mut Value) -> MigrationReport {
+    let from_version = config_version(doc);
+    let mut version = from_version;
+    let mut ran = vec![];
+
+    while version < CURRENT_VERSION {
+        let Some(migration) = migrations().into_iter().find(|m| m.from_version == version) else {
+            break;
+        };
+        (migration.apply)(doc);
+        ran.push(format!(
+            "{} -> {}: {}",
+            migration.from_version,
+            migration.from_version + 1,
+            migration.description
+        ));
+        version += 1;
+    }
+
+    if let Value::Mapping(map) = doc {
+        map.insert(Value::String("version".to_string()), Value::Number(version.into()));
+    }
+
+    MigrationReport {
+        from_version,
+        to_version: version,
+        ran,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_upgrade_migrates_v0_psql_and_mysql() {
+        let mut doc: Value = serde_yaml::from_str(
+            r#"
+            backups:
+              - name: db
+                type: psql
+                host: localhost
+                user: postgres
+                password: secret
+                port: 5432
+              - name: otherdb
+                type: mysql
+                host: localhost
+                user: root
+                password: secret
+                port: 3306
+            "#,
+        )
+        .unwrap();
+
+        let report = upgrade(&mut doc);
+
+        assert_eq!(report.from_version, 0);
+        assert_eq!(report.to_version, CURRENT_VERSION);
+        assert!(report.changed());
+        assert_eq!(report.ran.len(), 1);
+        assert_eq!(config_version(&doc), CURRENT_VERSION);
+
+        let backups = doc.get("backups").unwrap().as_sequence().unwrap();
+        assert_eq!(backups[0].get("type").unwrap().as_str(), Some("database"));
+        assert_eq!(backups[0].get("engine").unwrap().as_str(), Some("postgres"));
+        assert_eq!(backups[1].get("type").unwrap().as_str(), Some("database"));
+        assert_eq!(backups[1].get("engine").unwrap().as_str(), Some("mysql"));
+    }
+
+    #[test]
+    fn test_upgrade_current_version_is_noop() {
+        let mut doc: Value = serde_yaml::from_str(&format!(
+            r#"
+            version: {CURRENT_VERSION}
+            backups:
+              - name: db
+                type: database
+                engine: postgres
+                host: localhost
+                user: postgres
+                password: secret
+                port: 5432
+            "#
+        ))
+        .unwrap();
+        let before = doc.clone();
+
+        let report = upgrade(&mut doc);
+
+        assert_eq!(report.from_version, CURRENT_VERSION);
+        assert_eq!(report.to_version, CURRENT_VERSION);
+        assert!(!report.changed());
+        assert!(report.ran.is_empty());
+        assert_eq!(doc, before);
+    }
+}
@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use log::{error, info};
+use serde::Deserialize;
+
+use crate::utils::cmd::{run_argv_inherit, run_cmd};
+
+/// A single entry of a `borg list --json-lines` catalog. Only the fields
+/// the shell actually needs are kept; `borg` emits a lot more (mode,
+/// uid/gid, ...) that we don't care about here.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CatalogEntry {
+    pub path: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mtime: String,
+    #[serde(rename = "type", default)]
+    pub kind: String,
+}
+
+/// A browsable snapshot of an archive's file tree, built once from
+/// `borg list --json-lines` so the catalog shell can be navigated without
+/// keeping the (slow) FUSE mount alive for the whole session.
+#[derive(Debug, Default)]
+pub struct Catalog {
+    entries: HashMap<String, CatalogEntry>,
+    // Maps a directory path to the names of its direct children.
+    children: HashMap<String, Vec<String>>,
+}
+
+impl Catalog {
+    pub fn build(repo: &str, archive: &str) -> Option<Self> {
+        let cmd = format!("borg list --json-lines {repo}::{archive}");
+        let output = run_cmd(&cmd);
+        if !output.status.success() {
+            error!(
+                "Failed to list archive {archive}: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return None;
+        }
+
+        let mut catalog = Self::default();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<CatalogEntry>(line) {
+                Ok(entry) => catalog.insert(entry),
+                Err(e) => error!("Failed to parse catalog line {line}: {e}"),
+            }
+        }
+        Some(catalog)
+    }
+
+    fn insert(&mut self, entry: CatalogEntry) {
+        let path = entry.path.trim_end_matches('/').to_string();
+        if let Some((parent, name)) = path.rsplit_once('/') {
+            self.children
+                .entry(parent.to_string())
+                .or_default()
+                .push(name.to_string());
+        } else if !path.is_empty() {
+            self.children.entry(String::new()).or_default().push(path.clone());
+        }
+        self.entries.insert(path, entry);
+    }
+
+    pub fn ls(&self, dir: &str) -> Vec<String> {
+        let dir = dir.trim_end_matches('/');
+        let mut names = self.children.get(dir).cloned().unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    pub fn stat(&self, path: &str) -> Option<&CatalogEntry> {
+        self.entries.get(path.trim_end_matches('/'))
+    }
+
+    pub fn find(&self, needle: &str) -> Vec<&str> {
+        self.entries
+            .keys()
+            .filter(|p| p.contains(needle))
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Wraps `borg extract` so individual files can be pulled out of a single
+/// archive without a full restore. It does not mount the archive; FUSE
+/// mounting is done by `Borg::mount`.
+pub struct RestoreTarget {
+    pub repo: String,
+    pub archive: String,
+    pub mountpoint: PathBuf,
+}
+
+impl RestoreTarget {
+    pub fn new(repo: &str, archive: &str, mountpoint: impl AsRef<Path>) -> Self {
+        Self {
+            repo: repo.to_string(),
+            archive: archive.to_string(),
+            mountpoint: mountpoint.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Extract a single path from the archive into `dest` without
+    /// mounting it, so cherry-picking a file doesn't require keeping a
+    /// FUSE mount around.
+    pub fn extract(&self, path: &str, dest: impl AsRef<Path>) -> bool {
+        let dest = dest.as_ref();
+        std::fs::create_dir_all(dest).unwrap_or_default();
+        let archive_spec = format!("{}::{}", self.repo, self.archive);
+        // `borg extract` writes relative to the current working directory.
+        run_argv_inherit("borg", &["extract", &archive_spec, path], Some(dest))
+            .map(|status| status.success())
+            .unwrap_or(false)
+    }
+}
+
+/// A tiny `ls`/`cd`/`get`/`stat`/`find` REPL over a [`Catalog`], so users
+/// can cherry-pick files from an archive without extracting it whole.
+pub struct CatalogShell {
+    catalog: Catalog,
+    target: RestoreTarget,
+    cwd: String,
+}
+
+impl CatalogShell {
+    pub fn new(catalog: Catalog, target: RestoreTarget) -> Self {
+        Self {
+            catalog,
+            target,
+            cwd: String::new(),
+        }
+    }
+
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        loop {
+            print!("{}> ", self.cwd);
+            let _ = io::stdout().flush();
+            let mut line = String::new();
+            if stdin.read_line(&mut line).unwrap_or(0) == 0 {
+                break;
+            }
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, ' ');
+            let cmd = parts.next().unwrap_or_default();
+            let arg = parts.next().unwrap_or_default().trim();
+            match cmd {
+                "ls" => {
+                    let dir = if arg.is_empty() { &self.cwd } else { arg };
+                    for name in self.catalog.ls(dir) {
+                        println!("{name}");
+                    }
+                }
+                "cd" => self.cwd = arg.trim_start_matches('/').to_string(),
+                "stat" => match self.catalog.stat(&join(&self.cwd, arg)) {
+                    Some(entry) => println!("{entry:?}"),
+                    None => println!("no such path"),
+                },
+                "find" => {
+                    for path in self.catalog.find(arg) {
+                        println!("{path}");
+                    }
+                }
+                "get" => {
+                    let path = join(&self.cwd, arg);
+                    if self.target.extract(&path, &self.target.mountpoint) {
+                        info!("Extracted {path}");
+                    } else {
+                        println!("extract failed");
+                    }
+                }
+                "exit" | "quit" => break,
+                other => println!("unknown command: {other}"),
+            }
+        }
+    }
+}
+
+fn join(cwd: &str, path: &str) -> String {
+    if path.is_empty() {
+        cwd.to_string()
+    } else if cwd.is_empty() {
+        path.trim_start_matches('/').to_string()
+    } else {
+        format!("{cwd}/{}", path.trim_start_matches('/'))
+    }
+}
@@ -0,0 +1,152 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use serde_with::{DisplayFromStr, PickFirst};
+use void::Void;
+
+use crate::utils::{
+    cmd::run_argv,
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    mountable::Mountable,
+};
+
+fn default_snapshot_dir() -> String {
+    "/tmp/backup/btrfs".to_string()
+}
+
+/// Takes a read-only btrfs snapshot of each configured subvolume for
+/// point-in-time consistency, hands the snapshot path to borg, then
+/// deletes it again in `post_backup`.
+#[serde_with::serde_as]
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct BtrfsBackup {
+    /// Directory snapshots are staged under, one subdirectory per subvolume.
+    #[serde(default = "default_snapshot_dir")]
+    pub snapshot_dir: String,
+    #[serde_as(as = "Vec<PickFirst<(_, DisplayFromStr)>>")]
+    pub subvolumes: Vec<FolderEntry<BtrfsSubvolume>>,
+}
+
+impl Mountable for BtrfsBackup {
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        fs::create_dir_all(&self.snapshot_dir)?;
+        for f in &self.subvolumes {
+            let src = f.folder.path.to_str().unwrap_or_default();
+            let dest = f.folder.snapshot_path(&self.snapshot_dir);
+            run_argv("btrfs", &["subvolume", "snapshot", "-r", src, &dest])?;
+        }
+        Ok(())
+    }
+
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        for f in &self.subvolumes {
+            let dest = f.folder.snapshot_path(&self.snapshot_dir);
+            run_argv("btrfs", &["subvolume", "delete", &dest])?;
+        }
+        Ok(())
+    }
+
+    fn get_mount_path(&self) -> String {
+        self.snapshot_dir.clone()
+    }
+}
+
+#[typetag::serde(name = "btrfs")]
+impl BackupType for BtrfsBackup {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        self.mount()
+    }
+
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        self.unmount()
+    }
+
+    fn get_hostname(&self) -> String {
+        hostname::get()
+            .ok()
+            .and_then(|h| h.to_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
+        info!("Getting folders");
+        let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
+        for f in &self.subvolumes {
+            let mut folder = f.folder.clone();
+            folder.mounted_path = PathBuf::from(folder.snapshot_path(&self.snapshot_dir));
+            let dyn_folder: Box<dyn Folder> = Box::new(folder);
+            v.push(FolderEntry {
+                tags: f.tags.clone(),
+                folder: dyn_folder,
+            });
+        }
+        Ok(v)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct BtrfsSubvolume {
+    pub path: PathBuf,
+    #[serde(skip)]
+    mounted_path: PathBuf,
+}
+
+impl BtrfsSubvolume {
+    /// Stage this subvolume's snapshot under a name derived from its full
+    /// path rather than just its basename, so two subvolumes that share a
+    /// basename under different parents (`/mnt/a/data`, `/mnt/b/data`)
+    /// don't collide on the same staged snapshot path.
+    fn snapshot_path(&self, snapshot_dir: &str) -> String {
+        let trimmed = self.path.to_string_lossy().trim_matches('/').to_string();
+        let name = if trimmed.is_empty() {
+            "subvolume".to_string()
+        } else {
+            trimmed.replace('/', "-")
+        };
+        format!("{snapshot_dir}/{name}")
+    }
+}
+
+impl Folder for BtrfsSubvolume {
+    fn get_size(&self) -> Result<u64, BorgHelperError> {
+        let path = self.mounted_path.to_str().unwrap_or_default();
+        let output = run_argv("btrfs", &["filesystem", "du", "-s", "--raw", path])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let size = stdout
+            .lines()
+            .last()
+            .and_then(|line| line.split_whitespace().next())
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(0);
+        Ok(size)
+    }
+
+    fn get_path(&self) -> PathBuf {
+        self.mounted_path.clone()
+    }
+}
+
+impl FromStr for BtrfsSubvolume {
+    type Err = Void;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            path: PathBuf::from_str(s).unwrap(),
+            ..Default::default()
+        })
+    }
+}
+
+impl FromStr for FolderEntry<BtrfsSubvolume> {
+    type Err = Void;
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(Self {
+            folder: BtrfsSubvolume {
+                path: PathBuf::from_str(value).unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use log::info;
+use secstr::SecUtf8;
+use serde::{Deserialize, Serialize};
+
+use crate::sources::local::LocalFolder;
+use crate::utils::{
+    cmd::run_argv_with_env,
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    k8s::{start_k8s_proxy, K8sPortForward},
+    mountable::Mountable,
+    net::wait_for_port,
+    signals,
+};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DatabaseEngine {
+    Postgres,
+    #[serde(alias = "mariadb")]
+    Mysql,
+}
+
+impl DatabaseEngine {
+    fn list_databases(
+        &self,
+        host: &str,
+        port: &str,
+        user: &str,
+        password: &str,
+    ) -> Result<Vec<String>, BorgHelperError> {
+        let output = match self {
+            Self::Postgres => run_argv_with_env(
+                "psql",
+                &[
+                    "--host", host, "--port", port, "--username", user, "--dbname", "postgres",
+                    "--tuples-only", "--no-align", "--command",
+                    "SELECT datname FROM pg_database WHERE NOT datistemplate AND datname != 'postgres'",
+                ],
+                &[("PGPASSWORD", password)],
+            )?,
+            Self::Mysql => run_argv_with_env(
+                "mysql",
+                &["--host", host, "--port", port, "--user", user, "--skip-column-names", "--execute", "SHOW DATABASES"],
+                &[("MYSQL_PWD", password)],
+            )?,
+        };
+        let names = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .filter(|name| !matches!(*self, Self::Mysql) || !is_mysql_system_db(name))
+            .map(str::to_string)
+            .collect();
+        Ok(names)
+    }
+
+    fn dump_database(
+        &self,
+        host: &str,
+        port: &str,
+        user: &str,
+        password: &str,
+        database: &str,
+    ) -> Result<Vec<u8>, BorgHelperError> {
+        let output = match self {
+            Self::Postgres => run_argv_with_env(
+                "pg_dump",
+                &["--host", host, "--port", port, "--username", user, "--dbname", database],
+                &[("PGPASSWORD", password)],
+            )?,
+            Self::Mysql => run_argv_with_env(
+                "mysqldump",
+                &["--host", host, "--port", port, "--user", user, database],
+                &[("MYSQL_PWD", password)],
+            )?,
+        };
+        Ok(output.stdout)
+    }
+}
+
+fn is_mysql_system_db(name: &str) -> bool {
+    matches!(name, "information_schema" | "performance_schema" | "mysql" | "sys")
+}
+
+/// Dumps each database of a PostgreSQL or MySQL/MariaDB server to its own
+/// file under `get_mount_path()/<db>.sql`, so borg can dedupe and restore
+/// per-database instead of across one monolithic dump.
+#[derive(Serialize, Deserialize, Debug)]
+struct DatabaseBackup {
+    engine: DatabaseEngine,
+    user: String,
+    password: SecUtf8,
+    port: u16,
+    host: Option<String>,
+    /// Databases to dump. Empty means discover all (excluding the engine's
+    /// own system databases).
+    #[serde(default)]
+    databases: Vec<String>,
+    k8s_deployment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+
+    #[serde(skip)]
+    proxy_process: Arc<Mutex<Option<K8sPortForward>>>,
+    /// Cache of the discovered database list (only populated in discovery
+    /// mode), so whichever of `get_folders`/`mount` asks first pays for the
+    /// connection and the other reuses the answer.
+    #[serde(skip)]
+    discovered_databases: Mutex<Option<Vec<String>>>,
+}
+
+impl DatabaseBackup {
+    fn host(&self) -> String {
+        self.host.clone().unwrap_or("127.0.0.1".to_string())
+    }
+
+    /// Bring up the k8s port-forward (if configured) and return the host
+    /// to connect to. Idempotent: does nothing if a forward is already up.
+    fn ensure_connected(&self) -> Result<String, BorgHelperError> {
+        let host = self.host();
+        if let Some(deployment) = &self.k8s_deployment {
+            if self.proxy_process.lock().unwrap().is_none() {
+                let forward = start_k8s_proxy("default", deployment, self.port, self.port)?;
+                *self.proxy_process.lock().unwrap() = Some(forward);
+                signals::register(self.proxy_process.clone());
+                // The forwarded port can come up before the database inside
+                // the pod is actually accepting connections.
+                wait_for_port(&host, self.port, Duration::from_secs(30))?;
+            }
+        }
+        Ok(host)
+    }
+
+    /// The databases to dump: the configured list, or - in discovery mode
+    /// (empty `databases`) - the engine's own database list, queried once
+    /// and cached in `discovered_databases` regardless of whether
+    /// `get_folders` or `mount` triggers the discovery.
+    fn databases(&self) -> Result<Vec<String>, BorgHelperError> {
+        if !self.databases.is_empty() {
+            return Ok(self.databases.clone());
+        }
+        if let Some(cached) = self.discovered_databases.lock().unwrap().as_ref() {
+            return Ok(cached.clone());
+        }
+        let host = self.ensure_connected()?;
+        let port = self.port.to_string();
+        let discovered = self.engine.list_databases(&host, &port, &self.user, self.password.unsecure())?;
+        *self.discovered_databases.lock().unwrap() = Some(discovered.clone());
+        Ok(discovered)
+    }
+}
+
+impl Mountable for DatabaseBackup {
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        let host = self.ensure_connected()?;
+        let port = self.port.to_string();
+        let databases = self.databases()?;
+
+        fs::create_dir_all(self.get_mount_path())?;
+        for database in databases {
+            let dump = self.engine.dump_database(
+                &host,
+                &port,
+                &self.user,
+                self.password.unsecure(),
+                &database,
+            )?;
+            let path = format!("{}/{database}.sql", self.get_mount_path());
+            let mut f = File::create(path)?;
+            f.write_all(&dump)?;
+        }
+        Ok(())
+    }
+
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        info!("Unmounting database");
+        // Dropping the forward (if any) stops it deterministically.
+        self.proxy_process.lock().unwrap().take();
+        self.discovered_databases.lock().unwrap().take();
+        fs::remove_dir_all(self.get_mount_path())?;
+        Ok(())
+    }
+
+    fn get_mount_path(&self) -> String {
+        format!(
+            "/tmp/backup/database-{}",
+            self.host.clone().unwrap_or("nohost".into())
+        )
+    }
+}
+
+#[typetag::serde(name = "database")]
+impl BackupType for DatabaseBackup {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        self.mount()
+    }
+
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        self.unmount()
+    }
+
+    fn get_hostname(&self) -> String {
+        self.host.clone().unwrap_or(
+            self.k8s_deployment
+                .clone()
+                .unwrap_or("database".to_string()),
+        )
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
+        info!("Getting folders");
+        // Builds paths only, no I/O on the dump files themselves - they
+        // don't exist on disk yet until `mount` runs - but in discovery
+        // mode this does connect to the engine to list its databases
+        // (cached in `discovered_databases` so `mount` doesn't re-query).
+        let databases = self.databases()?;
+        let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
+        for database in &databases {
+            let dyn_folder: Box<dyn Folder> =
+                Box::new(LocalFolder::new(format!("{}/{database}.sql", self.get_mount_path())));
+            let mut tags = self.tags.clone();
+            tags.push(database.clone());
+            v.push(FolderEntry {
+                tags,
+                folder: dyn_folder,
+            });
+        }
+        Ok(v)
+    }
+}
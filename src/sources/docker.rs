@@ -0,0 +1,127 @@
+use std::{fs, path::PathBuf};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+
+use crate::sources::local::LocalFolder;
+use crate::utils::{
+    cmd::run_argv,
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    mountable::Mountable,
+};
+
+fn default_binary() -> String {
+    "docker".to_string()
+}
+
+/// Backs up named Docker/Podman volumes by resolving each one's host
+/// mountpoint and staging a copy of it under [`get_mount_path`], optionally
+/// pausing a set of containers from the copy until `post_backup` for write
+/// consistency.
+///
+/// [`get_mount_path`]: Mountable::get_mount_path
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct DockerBackup {
+    /// CLI to shell out to - `docker` or `podman`. Both speak the same
+    /// `volume inspect`/`pause`/`unpause` invocations used here.
+    #[serde(default = "default_binary")]
+    binary: String,
+    pub volumes: Vec<String>,
+    /// Containers to `pause` before the copy and `unpause` in
+    /// `post_backup`, so they stay paused while borg runs too. Leave empty
+    /// to copy volumes live.
+    #[serde(default)]
+    pub containers: Vec<String>,
+}
+
+impl DockerBackup {
+    fn volume_mount_path(&self, volume: &str) -> String {
+        format!("{}/{}", self.get_mount_path(), volume)
+    }
+
+    fn volume_host_path(&self, volume: &str) -> Result<String, BorgHelperError> {
+        let output = run_argv(
+            &self.binary,
+            &["volume", "inspect", "-f", "{{.Mountpoint}}", volume],
+        )?;
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    fn copy_volumes(&self) -> Result<(), BorgHelperError> {
+        fs::create_dir_all(self.get_mount_path())?;
+        for volume in &self.volumes {
+            let host_path = self.volume_host_path(volume)?;
+            let dest = self.volume_mount_path(volume);
+            run_argv("cp", &["-a", &host_path, &dest])?;
+        }
+        Ok(())
+    }
+
+    fn unpause_containers(&self) {
+        for container in &self.containers {
+            if let Err(e) = run_argv(&self.binary, &["unpause", container]) {
+                warn!("Failed to unpause container {container}: {e}");
+            }
+        }
+    }
+}
+
+impl Mountable for DockerBackup {
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        for container in &self.containers {
+            if let Err(e) = run_argv(&self.binary, &["pause", container]) {
+                warn!("Failed to pause container {container}, backing up live: {e}");
+            }
+        }
+
+        // Never leave containers paused if the copy fails partway through.
+        let result = self.copy_volumes();
+        if result.is_err() {
+            self.unpause_containers();
+        }
+        result
+    }
+
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        self.unpause_containers();
+        fs::remove_dir_all(self.get_mount_path())?;
+        Ok(())
+    }
+
+    fn get_mount_path(&self) -> String {
+        "/tmp/backup/docker".to_string()
+    }
+}
+
+#[typetag::serde(name = "docker")]
+impl BackupType for DockerBackup {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        self.mount()
+    }
+
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        self.unmount()
+    }
+
+    fn get_hostname(&self) -> String {
+        hostname::get()
+            .ok()
+            .and_then(|h| h.to_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
+        info!("Getting folders");
+        let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
+        for volume in &self.volumes {
+            let dyn_folder: Box<dyn Folder> =
+                Box::new(LocalFolder::new(PathBuf::from(self.volume_mount_path(volume))));
+            v.push(FolderEntry {
+                tags: vec![volume.clone()],
+                folder: dyn_folder,
+            });
+        }
+        Ok(v)
+    }
+}
@@ -1,19 +1,20 @@
 use std::{
-    cell::RefCell,
     fs::{self},
-    process::Child,
+    sync::{Arc, Mutex},
 };
 
-use log::{error, info};
+use log::info;
 use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 
 use crate::sources::local::LocalFolder;
 use crate::utils::{
-    cmd::run_cmd,
+    cmd::run_argv_with_env,
+    error::BorgHelperError,
     folder::{BackupType, Folder, FolderEntry},
-    k8s::start_k8s_proxy,
+    k8s::{start_k8s_proxy, K8sPortForward},
     mountable::Mountable,
+    signals,
 };
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,11 +28,11 @@ struct InfluxdbBackup {
     tags: Vec<String>,
 
     #[serde(skip)]
-    proxy_process: RefCell<Option<Child>>,
+    proxy_process: Arc<Mutex<Option<K8sPortForward>>>,
 }
 
 impl Mountable for InfluxdbBackup {
-    fn mount(&self) -> bool {
+    fn mount(&self) -> Result<(), BorgHelperError> {
         // If host is not set we assume localhost (or k8s)
         let host = self.host.clone().unwrap_or("http://127.0.0.1".to_string());
         let port = self.port.unwrap_or(8086);
@@ -39,36 +40,29 @@ impl Mountable for InfluxdbBackup {
         match &self.k8s_deployment {
             Some(deployment) => {
                 let namespace = self.k8s_namespace.clone().unwrap_or("default".to_string());
-                *self.proxy_process.borrow_mut() =
-                    start_k8s_proxy(&namespace, &deployment, port, port)
+                let forward = start_k8s_proxy(&namespace, deployment, port, port)?;
+                *self.proxy_process.lock().unwrap() = Some(forward);
+                signals::register(self.proxy_process.clone());
             }
             None => (),
         }
-        let cmd = format!(
-            "influx backup --host {}:{} --token {} {}",
-            host,
-            port,
-            self.token.clone().into_unsecure(),
-            self.get_mount_path()
-        );
-        let output = run_cmd(&cmd);
-        if !output.status.success() {
-            error!(
-                "Failed to dump database: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-            return false;
-        }
-        output.status.success()
+        // The token goes in via INFLUX_TOKEN, not `--token`, so it never
+        // shows up in `ps`.
+        let addr = format!("{host}:{port}");
+        run_argv_with_env(
+            "influx",
+            &["backup", "--host", &addr, &self.get_mount_path()],
+            &[("INFLUX_TOKEN", self.token.unsecure())],
+        )?;
+        Ok(())
     }
 
-    fn unmount(&self) -> bool {
-        info!("Unmounting psql");
-        if let Some(ref mut child) = *self.proxy_process.borrow_mut() {
-            child.kill().unwrap();
-        }
-        let res = fs::remove_file(self.get_mount_path());
-        res.is_ok()
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        info!("Unmounting influxdb");
+        // Dropping the forward (if any) stops it deterministically.
+        self.proxy_process.lock().unwrap().take();
+        fs::remove_file(self.get_mount_path())?;
+        Ok(())
     }
 
     fn get_mount_path(&self) -> String {
@@ -79,11 +73,11 @@ impl Mountable for InfluxdbBackup {
 
 #[typetag::serde(name = "influxdb")]
 impl BackupType for InfluxdbBackup {
-    fn pre_backup(&self) -> bool {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
         self.mount()
     }
 
-    fn post_backup(&self) -> bool {
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
         self.unmount()
     }
 
@@ -95,16 +89,15 @@ impl BackupType for InfluxdbBackup {
         )
     }
 
-    fn get_folders(&self) -> Vec<FolderEntry<Box<dyn Folder>>> {
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
         info!("Getting folders");
         let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
         let dyn_folder: Box<dyn Folder> = Box::new(LocalFolder::new(&self.get_mount_path()));
         let fe = FolderEntry {
             tags: self.tags.clone(),
             folder: dyn_folder,
-            options: None,
         };
         v.push(fe);
-        v
+        Ok(v)
     }
 }
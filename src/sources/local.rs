@@ -1,5 +1,4 @@
 use std::{
-    error::Error,
     fs,
     os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
@@ -10,7 +9,10 @@ use serde::{Deserialize, Serialize};
 use serde_with::{DisplayFromStr, PickFirst};
 use void::Void;
 
-use crate::utils::folder::{BackupType, Folder, FolderEntry};
+use crate::utils::{
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+};
 
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct LocalFolder {
@@ -28,7 +30,7 @@ impl LocalFolder {
     }
 }
 
-fn get_path_size(path: PathBuf) -> Result<u64, Box<dyn Error>> {
+fn get_path_size(path: PathBuf) -> Result<u64, BorgHelperError> {
     let mut total_size = 0;
     if path.is_dir() {
         for entry in fs::read_dir(path)? {
@@ -48,7 +50,7 @@ fn get_path_size(path: PathBuf) -> Result<u64, Box<dyn Error>> {
 }
 
 impl Folder for LocalFolder {
-    fn get_size(&self) -> Result<u64, Box<dyn Error>> {
+    fn get_size(&self) -> Result<u64, BorgHelperError> {
         get_path_size(self.get_path())
     }
 
@@ -87,29 +89,31 @@ pub(crate) struct LocalBackup {
 
 #[typetag::serde(name = "local")]
 impl BackupType for LocalBackup {
-    fn pre_backup(&self) -> bool {
-        true
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        Ok(())
     }
 
-    fn post_backup(&self) -> bool {
-        true
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        Ok(())
     }
 
     fn get_hostname(&self) -> String {
-        hostname::get().unwrap().to_str().unwrap().to_string()
+        hostname::get()
+            .ok()
+            .and_then(|h| h.to_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
     }
 
-    fn get_folders(&self) -> Vec<FolderEntry<Box<dyn Folder>>> {
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
         let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
         for f in &self.folders {
             let bf: Box<dyn Folder> = Box::new(f.folder.clone());
             let fe = FolderEntry {
                 tags: f.tags.clone(),
                 folder: bf,
-                options: f.options.clone(),
             };
             v.push(fe);
         }
-        v
+        Ok(v)
     }
 }
@@ -0,0 +1,157 @@
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use log::info;
+use mktemp::Temp;
+use secstr::SecUtf8;
+use serde::{Deserialize, Serialize};
+
+use crate::utils::{
+    cmd::run_argv,
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    k8s::{start_k8s_proxy, K8sPortForward},
+    mountable::Mountable,
+    signals,
+};
+
+/// The subset of `mongodump --config` fields we need - just enough to keep
+/// the password out of argv, serialized with serde_yaml so a `"` or `\` in
+/// it round-trips as valid YAML instead of corrupting a hand-quoted string.
+#[derive(Serialize)]
+struct MongoDumpConfig {
+    password: String,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct MongoBackup {
+    user: String,
+    password: SecUtf8,
+    port: u16,
+    host: Option<String>,
+    k8s_deployment: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+
+    #[serde(skip)]
+    proxy_process: Arc<Mutex<Option<K8sPortForward>>>,
+}
+
+impl Mountable for MongoBackup {
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        // If host is not set we assume localhost (or k8s)
+        let host = self.host.clone().unwrap_or("127.0.0.1".to_string());
+        // Create proxy connection
+        match &self.k8s_deployment {
+            Some(deployment) => {
+                let forward = start_k8s_proxy("default", deployment, self.port, self.port)?;
+                *self.proxy_process.lock().unwrap() = Some(forward);
+                signals::register(self.proxy_process.clone());
+            }
+            None => (),
+        }
+
+        // mongodump has no env var for its password the way pg_dumpall/
+        // mysqldump do, so it goes through a temporary --config file
+        // instead of argv, keeping it out of `ps`. Serialize it through
+        // serde_yaml rather than hand-formatting the quoted scalar, so a
+        // `"` or `\` in the password can't corrupt the document.
+        let config_file = Temp::new_file()?;
+        let mut config = File::create(&config_file)?;
+        config.write_all(serde_yaml::to_string(&MongoDumpConfig {
+            password: self.password.unsecure().to_string(),
+        })?.as_bytes())?;
+        drop(config);
+
+        let port = self.port.to_string();
+        let config_path = config_file.to_str().unwrap_or_default();
+        run_argv(
+            "mongodump",
+            &[
+                "--host",
+                &host,
+                "--port",
+                &port,
+                "--username",
+                &self.user,
+                "--config",
+                config_path,
+                "--archive",
+                &self.get_mount_path(),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        info!("Unmounting mongo");
+        // Dropping the forward (if any) stops it deterministically.
+        self.proxy_process.lock().unwrap().take();
+        fs::remove_file(self.get_mount_path())?;
+        Ok(())
+    }
+
+    fn get_mount_path(&self) -> String {
+        format!(
+            "/tmp/backup/mongo-{}",
+            self.host.clone().unwrap_or("nohost".into())
+        )
+    }
+}
+
+#[typetag::serde(name = "mongo")]
+impl BackupType for MongoBackup {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        self.mount()
+    }
+
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        self.unmount()
+    }
+
+    fn get_hostname(&self) -> String {
+        self.host.clone().unwrap_or(
+            self.k8s_deployment
+                .clone()
+                .unwrap_or("mongo_back".to_string()),
+        )
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
+        info!("Getting folders");
+        let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
+        let dyn_folder: Box<dyn Folder> = Box::new(MongoFolder::new(&self.get_mount_path()));
+        let fe = FolderEntry {
+            tags: self.tags.clone(),
+            folder: dyn_folder,
+        };
+        v.push(fe);
+        Ok(v)
+    }
+}
+
+struct MongoFolder {
+    path: String,
+}
+
+impl MongoFolder {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+}
+
+impl Folder for MongoFolder {
+    fn get_size(&self) -> Result<u64, BorgHelperError> {
+        Ok(fs::metadata(&self.path)?.len())
+    }
+
+    fn get_path(&self) -> PathBuf {
+        PathBuf::from(self.path.clone())
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    fs,
+    io::{BufRead, BufReader, Write},
+    os::unix::net::UnixStream,
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::sources::local::LocalFolder;
+use crate::utils::{
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    mountable::Mountable,
+};
+
+/// Send `command` (with optional `arguments`) over an already-negotiated QMP
+/// connection and return its `"return"` value, or a [`BorgHelperError`] if
+/// the monitor reports an `"error"`.
+fn qmp_execute(stream: &mut UnixStream, command: &str, arguments: Option<Value>) -> Result<Value, BorgHelperError> {
+    let mut request = json!({ "execute": command });
+    if let Some(arguments) = arguments {
+        request["arguments"] = arguments;
+    }
+    let line = format!("{}\n", request);
+    stream
+        .write_all(line.as_bytes())
+        .map_err(|e| BorgHelperError::command_io(format!("qmp {command}"), e))?;
+
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| BorgHelperError::command_io(command, e))?);
+    loop {
+        let mut response = String::new();
+        reader
+            .read_line(&mut response)
+            .map_err(|e| BorgHelperError::command_io(format!("qmp {command}"), e))?;
+        let value: Value = serde_json::from_str(&response)?;
+        // Events can arrive interleaved with the reply we're waiting for.
+        if value.get("event").is_some() {
+            continue;
+        }
+        if let Some(error) = value.get("error") {
+            return Err(BorgHelperError::MountFailed {
+                what: format!("qmp {command}"),
+                reason: error.to_string(),
+            });
+        }
+        return Ok(value.get("return").cloned().unwrap_or(Value::Null));
+    }
+}
+
+/// Connect to a QMP unix socket and negotiate capabilities so regular
+/// commands (as opposed to the greeting) can be issued.
+fn connect_qmp(socket: &PathBuf) -> Result<UnixStream, BorgHelperError> {
+    let mut stream = UnixStream::connect(socket).map_err(|e| BorgHelperError::command_io(socket.display().to_string(), e))?;
+    // Discard the greeting banner before negotiating capabilities.
+    let mut reader = BufReader::new(stream.try_clone().map_err(|e| BorgHelperError::command_io(socket.display().to_string(), e))?);
+    let mut greeting = String::new();
+    reader
+        .read_line(&mut greeting)
+        .map_err(|e| BorgHelperError::command_io(socket.display().to_string(), e))?;
+    qmp_execute(&mut stream, "qmp_capabilities", None)?;
+    Ok(stream)
+}
+
+/// Look up the image file `device` is currently backed by, via
+/// `query-block`. Call this *before* `blockdev-snapshot-sync` diverts new
+/// writes into an overlay - at that point it's still the file actually
+/// holding the guest's data, which is what we want to back up once it's
+/// frozen by the snapshot rather than the overlay that keeps changing
+/// underneath borg for the rest of the backup.
+fn query_backing_path(stream: &mut UnixStream, device: &str) -> Result<PathBuf, BorgHelperError> {
+    let not_found = || BorgHelperError::MountFailed {
+        what: device.to_string(),
+        reason: "device not found in query-block, or it has no backing image filename".to_string(),
+    };
+    let blocks = qmp_execute(stream, "query-block", None)?;
+    let filename = blocks
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|block| block.get("device").and_then(Value::as_str) == Some(device))
+        .and_then(|block| block.get("inserted"))
+        .and_then(|inserted| inserted.get("image"))
+        .and_then(|image| image.get("filename"))
+        .and_then(Value::as_str)
+        .ok_or_else(not_found)?;
+    Ok(PathBuf::from(filename))
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct QemuBackup {
+    /// Path to the VM's QMP unix socket.
+    qmp_socket: PathBuf,
+    /// Path to the guest agent's unix socket. If unset, the filesystem is
+    /// snapshotted without a freeze/thaw.
+    guest_agent_socket: Option<PathBuf>,
+    /// Name of the block device/node to snapshot (the `device` argument to
+    /// `blockdev-snapshot-sync`).
+    device: String,
+    #[serde(default)]
+    tags: Vec<String>,
+
+    /// Scratch overlay that absorbs the guest's writes for the duration of
+    /// the backup; never itself backed up.
+    #[serde(skip)]
+    overlay_path: Mutex<Option<PathBuf>>,
+    /// The frozen backing image borg actually reads - what `device`
+    /// pointed at right before the overlay took over.
+    #[serde(skip)]
+    backing_path: Mutex<Option<PathBuf>>,
+}
+
+impl QemuBackup {
+    fn overlay_path(&self) -> PathBuf {
+        PathBuf::from(format!("/tmp/backup/qemu-{}.overlay", self.device))
+    }
+
+    /// Resolve (and cache) the image file `device` is currently backed by,
+    /// via a fresh QMP `query-block`, so whichever of `get_folders`/`mount`
+    /// asks first pays for the connection and the other reuses the answer.
+    /// `get_folders` always runs before `mount` in the backup driver, so
+    /// this also naturally satisfies `query_backing_path`'s "before the
+    /// snapshot diverts writes" ordering requirement.
+    fn backing_path(&self) -> Result<PathBuf, BorgHelperError> {
+        if let Some(cached) = self.backing_path.lock().unwrap().as_ref() {
+            return Ok(cached.clone());
+        }
+        let mut stream = connect_qmp(&self.qmp_socket)?;
+        let backing_path = query_backing_path(&mut stream, &self.device)?;
+        *self.backing_path.lock().unwrap() = Some(backing_path.clone());
+        Ok(backing_path)
+    }
+
+    fn freeze_guest(&self) -> Result<bool, BorgHelperError> {
+        let Some(socket) = &self.guest_agent_socket else {
+            return Ok(false);
+        };
+        // The guest agent speaks bare QMP commands without capability
+        // negotiation or a greeting.
+        let mut stream = UnixStream::connect(socket).map_err(|e| BorgHelperError::command_io(socket.display().to_string(), e))?;
+        match qmp_execute(&mut stream, "guest-fsfreeze-freeze", None) {
+            Ok(_) => Ok(true),
+            Err(e) => {
+                warn!("guest-fsfreeze-freeze failed, continuing without a freeze: {e}");
+                Ok(false)
+            }
+        }
+    }
+
+    fn thaw_guest(&self) {
+        let Some(socket) = &self.guest_agent_socket else {
+            return;
+        };
+        match UnixStream::connect(socket) {
+            Ok(mut stream) => {
+                if let Err(e) = qmp_execute(&mut stream, "guest-fsfreeze-thaw", None) {
+                    warn!("guest-fsfreeze-thaw failed: {e}");
+                }
+            }
+            Err(e) => warn!("failed to reconnect to guest agent to thaw: {e}"),
+        }
+    }
+}
+
+impl Mountable for QemuBackup {
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        let overlay = self.overlay_path();
+        if let Some(parent) = overlay.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        // Resolves the backing path if `get_folders` (which runs first in
+        // every real flow) hasn't already cached it.
+        self.backing_path()?;
+
+        let mut stream = connect_qmp(&self.qmp_socket)?;
+        let frozen = self.freeze_guest()?;
+
+        let snapshot_result = qmp_execute(
+            &mut stream,
+            "blockdev-snapshot-sync",
+            Some(json!({
+                "device": self.device,
+                "snapshot-file": overlay.to_string_lossy(),
+                "format": "qcow2",
+            })),
+        );
+
+        if frozen {
+            self.thaw_guest();
+        }
+        snapshot_result?;
+
+        *self.overlay_path.lock().unwrap() = Some(overlay);
+        Ok(())
+    }
+
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        // Thaw defensively in case the guest is still frozen; mount()
+        // already thaws right after the snapshot attempt, so normally this
+        // is a no-op.
+        self.thaw_guest();
+
+        self.backing_path.lock().unwrap().take();
+        if let Some(overlay) = self.overlay_path.lock().unwrap().take() {
+            // Fold the overlay back into the backing image so the VM keeps
+            // using its original chain, then drop the temporary file.
+            if let Ok(mut stream) = connect_qmp(&self.qmp_socket) {
+                let _ = qmp_execute(
+                    &mut stream,
+                    "block-commit",
+                    Some(json!({ "device": self.device })),
+                );
+            }
+            fs::remove_file(&overlay)?;
+        }
+        Ok(())
+    }
+
+    fn get_mount_path(&self) -> String {
+        self.backing_path
+            .lock()
+            .unwrap()
+            .clone()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+#[typetag::serde(name = "qemu")]
+impl BackupType for QemuBackup {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        self.mount()
+    }
+
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        self.unmount()
+    }
+
+    fn get_hostname(&self) -> String {
+        self.device.clone()
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
+        info!("Getting folders");
+        // This runs before `pre_backup`/`mount`, so resolve the backing
+        // path ourselves instead of assuming `mount` already populated it.
+        self.backing_path()?;
+        let dyn_folder: Box<dyn Folder> = Box::new(LocalFolder::new(self.get_mount_path()));
+        let fe = FolderEntry {
+            tags: self.tags.clone(),
+            folder: dyn_folder,
+        };
+        Ok(vec![fe])
+    }
+}
@@ -3,7 +3,8 @@ use std::{collections::HashMap, str::FromStr};
 use serde::{Deserialize, Serialize};
 
 use crate::utils::{
-    cmd::run_cmd_checked,
+    cmd::run_checked,
+    error::BorgHelperError,
     folder::{BackupType, Folder, FolderEntry},
 };
 
@@ -46,21 +47,28 @@ struct SaveBackup {
 
 #[typetag::serde(name = "saves")]
 impl BackupType for SaveBackup {
-    fn pre_backup(&self) -> bool {
-        true
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
+        Ok(())
     }
 
-    fn post_backup(&self) -> bool {
-        true
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
+        Ok(())
     }
 
-    fn get_folders(&self) -> Vec<FolderEntry<Box<dyn Folder>>> {
+    fn get_hostname(&self) -> String {
+        hostname::get()
+            .ok()
+            .and_then(|h| h.to_str().map(str::to_string))
+            .unwrap_or_else(|| "unknown".to_string())
+    }
+
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
         let binary = self.binary.clone().unwrap_or("ludusavi".to_string());
 
-        let output = run_cmd_checked(&format!("{} backup --preview --api", binary)).unwrap();
+        let output = run_checked(&format!("{} backup --preview --api", binary))?;
         let output_str = String::from_utf8(output.stdout).unwrap_or_default();
 
-        let json_data: JsonOutput = serde_yaml::from_str(&output_str).unwrap();
+        let json_data: JsonOutput = serde_yaml::from_str(&output_str)?;
 
         let files: Vec<FolderEntry<Box<dyn Folder>>> = json_data
             .games
@@ -82,7 +90,6 @@ impl BackupType for SaveBackup {
                         let fe = FolderEntry {
                             tags: tags.clone(),
                             folder: bf,
-                            options: None,
                         };
                         entries.push(fe);
                     }
@@ -92,7 +99,7 @@ impl BackupType for SaveBackup {
             })
             .flatten()
             .collect();
-        files
+        Ok(files)
     }
 }
 
@@ -106,7 +113,7 @@ mod test {
     #[test]
     fn test_ludusavi() {
         let back = SaveBackup::default();
-        let folders = back.get_folders();
+        let folders = back.get_folders().unwrap();
         assert_ge!(folders.len(), 1);
     }
 
@@ -121,7 +128,7 @@ mod test {
             tags: vec!["global_tag".to_string()],
             binary: None,
         };
-        let folders = back.get_folders();
+        let folders = back.get_folders().unwrap();
         assert_ge!(folders.len(), 1);
 
         assert!(folders
@@ -1,40 +1,75 @@
-use std::{error::Error, path::PathBuf, str::FromStr};
+use std::{
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
 
-use log::info;
+use log::{info, warn};
+use secstr::SecUtf8;
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr, PickFirst};
 use void::Void;
 
-use crate::{
-    run_cmd,
-    utils::{
-        folder::{BackupType, Folder, FolderEntry},
-        mountable::Mountable,
-    },
+use crate::utils::{
+    cmd::run_argv,
+    error::BorgHelperError,
+    folder::{BackupType, Folder, FolderEntry},
+    mountable::Mountable,
+    ssh_session::SshSession,
 };
 
+type SessionSlot = Arc<Mutex<Option<SshSession>>>;
+
 #[serde_with::serde_as]
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct SSHBackup {
     pub target: String,
+    /// Private key to authenticate with, also passed to `sshfs` as
+    /// `-o IdentityFile=`. Tried after ssh-agent, before `password`.
+    pub identity: Option<PathBuf>,
+    pub password: Option<SecUtf8>,
+    /// Skip verifying the remote host key against `~/.ssh/known_hosts`.
+    /// Off by default, matching `ssh`/`sshfs`'s `StrictHostKeyChecking`;
+    /// only set this for an ephemeral/throwaway host.
+    #[serde(default)]
+    pub insecure_skip_host_key_check: bool,
     #[serde_as(as = "Vec<PickFirst<(_, DisplayFromStr)>>")]
     pub folders: Vec<FolderEntry<SSHFolder>>,
+
+    #[serde(skip)]
+    session: SessionSlot,
 }
 
 impl Mountable for SSHBackup {
-    fn mount(&self) -> bool {
-        // TODO: use key
+    fn mount(&self) -> Result<(), BorgHelperError> {
+        let session = SshSession::connect(
+            &self.target,
+            self.identity.as_deref(),
+            self.password.as_ref().map(SecUtf8::unsecure),
+            self.insecure_skip_host_key_check,
+        )?;
+        *self.session.lock().unwrap() = Some(session);
+
         let temp_dir = self.get_mount_path();
-        std::fs::create_dir_all(&temp_dir).unwrap_or_default();
-        let cmd = format!("sshfs {}:/ {temp_dir}", self.target);
-        let output = run_cmd(&cmd);
-        output.status.success()
+        std::fs::create_dir_all(&temp_dir)?;
+        let remote = format!("{}:/", self.target);
+        match &self.identity {
+            Some(identity) => {
+                let identity_opt = format!("IdentityFile={}", identity.display());
+                run_argv("sshfs", &["-o", &identity_opt, &remote, &temp_dir])?;
+            }
+            None => {
+                run_argv("sshfs", &[&remote, &temp_dir])?;
+            }
+        }
+        Ok(())
     }
 
-    fn unmount(&self) -> bool {
-        let cmd = format!("fusermount -u {}", self.get_mount_path());
-        let output = run_cmd(&cmd);
-        output.status.success()
+    fn unmount(&self) -> Result<(), BorgHelperError> {
+        run_argv("fusermount", &["-u", &self.get_mount_path()])?;
+        // Dropping the session closes the connection.
+        self.session.lock().unwrap().take();
+        Ok(())
     }
 
     fn get_mount_path(&self) -> String {
@@ -44,11 +79,11 @@ impl Mountable for SSHBackup {
 
 #[typetag::serde(name = "ssh")]
 impl BackupType for SSHBackup {
-    fn pre_backup(&self) -> bool {
+    fn pre_backup(&self) -> Result<(), BorgHelperError> {
         self.mount()
     }
 
-    fn post_backup(&self) -> bool {
+    fn post_backup(&self) -> Result<(), BorgHelperError> {
         self.unmount()
     }
 
@@ -65,13 +100,14 @@ impl BackupType for SSHBackup {
         String::from("--files-cache ctime,size")
     }
 
-    fn get_folders(&self) -> Vec<FolderEntry<Box<dyn Folder>>> {
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError> {
         info!("Getting folders");
         let mut v: Vec<FolderEntry<Box<dyn Folder>>> = vec![];
         for f in &self.folders {
             let mut folder = f.folder.clone();
             folder.prefix = PathBuf::from(self.get_mount_path());
             folder.target = self.target.clone();
+            folder.session = self.session.clone();
             let dyn_folder: Box<dyn Folder> = Box::new(folder);
             let fe = FolderEntry {
                 tags: f.tags.clone(),
@@ -79,7 +115,7 @@ impl BackupType for SSHBackup {
             };
             v.push(fe);
         }
-        v
+        Ok(v)
     }
 }
 
@@ -90,31 +126,32 @@ pub struct SSHFolder {
     pub prefix: PathBuf,
     #[serde(skip)]
     pub target: String,
+    #[serde(skip)]
+    session: SessionSlot,
 }
 
 impl Folder for SSHFolder {
-    fn get_size(&self) -> Result<u64, Box<dyn Error>> {
-        // SSH to target and call "du <folder>". It is way faster than using the mounted fs
-        let remote_cmd = format!(
-            "du -s {} 2>/dev/null | cut -f1",
-            self.path.to_str().unwrap_or_default()
-        );
-
-        let cmd = format!("ssh {} {}", self.target, remote_cmd);
-
-        let output = run_cmd(&cmd);
-        if output.status.success() {
-            let output_str: String = std::str::from_utf8(&output.stdout)
-                .unwrap()
-                .chars()
-                .filter(|c| !c.is_whitespace())
-                .collect();
-            info!("{}", output_str);
-
-            let val = output_str.parse::<u64>().unwrap_or(0);
-            return Ok(val);
+    fn get_size(&self) -> Result<u64, BorgHelperError> {
+        // Run over the shared, already-authenticated session instead of
+        // spawning a fresh `ssh` process per folder.
+        let guard = self.session.lock().unwrap();
+        let Some(session) = guard.as_ref() else {
+            warn!("No open ssh session for {}, reporting size 0", self.target);
+            return Ok(0);
+        };
+        let path = self.path.to_str().unwrap_or_default();
+        let (stdout, status) = session.exec(&format!("du -sk {path} 2>/dev/null"))?;
+        if status != 0 {
+            return Ok(0);
         }
-        Ok(0)
+        // `du -k` reports KiB; callers expect bytes.
+        let size = stdout
+            .split_whitespace()
+            .next()
+            .and_then(|s| s.parse::<u64>().ok())
+            .map(|kib| kib * 1024)
+            .unwrap_or(0);
+        Ok(size)
     }
 
     fn get_path(&self) -> PathBuf {
@@ -1,21 +1,34 @@
-use std::process::{Child, Command, Output, Stdio};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
 
 use log::info;
 
-pub fn run_cmd(cmd: &str) -> Output {
+use super::error::BorgHelperError;
+
+/// Run `cmd` and map IO/exit failures onto [`BorgHelperError`] instead of
+/// panicking. Prefer this over `run_cmd` in any path that can surface its
+/// error to the user (sources, repo checks) rather than aborting the run.
+pub fn run_checked(cmd: &str) -> Result<Output, BorgHelperError> {
     info!("Calling \"{}\"", cmd);
     let output = Command::new("sh")
         .arg("-c")
         .arg(cmd)
         .output()
-        .expect("failed to execute process");
-
-    output
+        .map_err(|e| BorgHelperError::command_io(cmd, e))?;
+    if !output.status.success() {
+        return Err(BorgHelperError::from_output(cmd, &output));
+    }
+    Ok(output)
 }
 
-pub fn run_cmd_checked(cmd: &str) -> Result<Output, std::io::Error> {
+pub fn run_cmd(cmd: &str) -> Output {
     info!("Calling \"{}\"", cmd);
-    let output = Command::new("sh").arg("-c").arg(cmd).output();
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .output()
+        .expect("failed to execute process");
 
     output
 }
@@ -63,3 +76,90 @@ pub fn run_cmd_piped(cmd: &str) -> Output {
 pub fn run_cmd_background(cmd: &str) -> Result<Child, std::io::Error> {
     Command::new("sh").arg("-c").arg(cmd).spawn()
 }
+
+/// Like `run_cmd_inherit`, but also streams stderr to `on_line` line by
+/// line as it's produced, instead of buffering it until the command
+/// exits. Stdout still goes straight to the terminal. Use this to follow
+/// `borg create --progress --log-json`'s live output, which borg (like
+/// most of its other status/log output) writes to stderr rather than
+/// stdout.
+pub fn run_cmd_streaming(cmd: &str, mut on_line: impl FnMut(&str)) -> ExitStatus {
+    info!("Calling streaming \"{}\"", cmd);
+    let mut child = Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stdout(Stdio::inherit())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to execute process");
+
+    if let Some(stderr) = child.stderr.take() {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            on_line(&line);
+        }
+    }
+
+    child.wait().expect("failed to wait on process")
+}
+
+/// Run `program` with `args` directly, with no shell in between. Use this
+/// instead of `run_checked`/`run_cmd` whenever any of the arguments come
+/// from config (paths, hostnames, deployment names) or carry a secret -
+/// there is no interpolation into a shell string for an attacker to break
+/// out of, and nothing is ever handed to `sh -c`.
+pub fn run_argv(program: &str, args: &[&str]) -> Result<Output, BorgHelperError> {
+    run_argv_with_env(program, args, &[])
+}
+
+/// Like [`run_argv`], but sets additional environment variables on the
+/// child instead of baking them into the command line, so a secret (a
+/// DB password, an API token, ...) never shows up in `ps`.
+pub fn run_argv_with_env(
+    program: &str,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<Output, BorgHelperError> {
+    let display = format!("{program} {}", args.join(" "));
+    info!("Calling \"{}\"", display);
+    let output = Command::new(program)
+        .args(args)
+        .envs(envs.iter().copied())
+        .output()
+        .map_err(|e| BorgHelperError::command_io(display.clone(), e))?;
+    if !output.status.success() {
+        return Err(BorgHelperError::from_output(display, &output));
+    }
+    Ok(output)
+}
+
+/// Like [`run_argv`], but inherits stdout/stderr instead of capturing
+/// them, and optionally runs in `cwd` first. Use this for foreground
+/// commands whose own progress output should reach the user directly
+/// (e.g. `borg extract`, `borg export-tar`) while still avoiding a shell
+/// for arguments that come from user/CLI input.
+pub fn run_argv_inherit(
+    program: &str,
+    args: &[&str],
+    cwd: Option<&Path>,
+) -> Result<ExitStatus, BorgHelperError> {
+    let display = format!("{program} {}", args.join(" "));
+    info!("Calling \"{}\"", display);
+    let mut command = Command::new(program);
+    command.args(args);
+    if let Some(cwd) = cwd {
+        command.current_dir(cwd);
+    }
+    command
+        .status()
+        .map_err(|e| BorgHelperError::command_io(display, e))
+}
+
+/// Spawn `program` with `args` in the background, optionally with extra
+/// environment variables, without going through a shell.
+pub fn spawn_argv_with_env(
+    program: &str,
+    args: &[&str],
+    envs: &[(&str, &str)],
+) -> Result<Child, std::io::Error> {
+    Command::new(program).args(args).envs(envs.iter().copied()).spawn()
+}
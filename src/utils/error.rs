@@ -0,0 +1,52 @@
+use thiserror::Error;
+
+/// Crate-wide error type. Call sites that used to `.unwrap()`/`.expect()`
+/// external commands or parse untrusted output should instead return one
+/// of these so a single bad source (missing binary, malformed JSON/YAML,
+/// non-zero exit) produces an actionable message and gets skipped rather
+/// than aborting the whole run.
+#[derive(Error, Debug)]
+pub enum BorgHelperError {
+    #[error("failed to run \"{cmd}\": {source}")]
+    CommandIo {
+        cmd: String,
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("\"{cmd}\" exited with {status}: {stderr}")]
+    CommandFailed {
+        cmd: String,
+        status: std::process::ExitStatus,
+        stderr: String,
+    },
+
+    #[error("failed to parse YAML output: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("failed to parse JSON output: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("failed to mount {what}: {reason}")]
+    MountFailed { what: String, reason: String },
+
+    #[error("failed to start k8s proxy for {deployment}: {reason}")]
+    K8sProxyFailed { deployment: String, reason: String },
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl BorgHelperError {
+    pub fn command_io(cmd: impl Into<String>, source: std::io::Error) -> Self {
+        Self::CommandIo { cmd: cmd.into(), source }
+    }
+
+    pub fn from_output(cmd: impl Into<String>, output: &std::process::Output) -> Self {
+        Self::CommandFailed {
+            cmd: cmd.into(),
+            status: output.status,
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        }
+    }
+}
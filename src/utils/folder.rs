@@ -1,27 +1,29 @@
-use std::{error::Error, fmt::Debug, path::PathBuf};
+use std::{fmt::Debug, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use super::error::BorgHelperError;
+
 #[typetag::serde(tag = "type")]
 pub trait BackupType: Debug {
-    fn pre_backup(&self) -> bool;
-    fn post_backup(&self) -> bool;
+    fn pre_backup(&self) -> Result<(), BorgHelperError>;
+    fn post_backup(&self) -> Result<(), BorgHelperError>;
     fn get_hostname(&self) -> String;
     // TODO: I don't like this. Just returning a Vec<impl Folder> would be nice
     // Vec<Box<dyn Folder>> won't work as well :/
-    fn get_folders(&self) -> Vec<FolderEntry<Box<dyn Folder>>>;
+    fn get_folders(&self) -> Result<Vec<FolderEntry<Box<dyn Folder>>>, BorgHelperError>;
     fn get_additional_options(&self) -> String {
         String::new()
     }
 }
 
 pub trait Folder {
-    fn get_size(&self) -> Result<u64, Box<dyn Error>>;
+    fn get_size(&self) -> Result<u64, BorgHelperError>;
     fn get_path(&self) -> PathBuf;
 }
 
 impl<F: Folder + ?Sized> Folder for Box<F> {
-    fn get_size(&self) -> Result<u64, Box<dyn Error>> {
+    fn get_size(&self) -> Result<u64, BorgHelperError> {
         (**self).get_size()
     }
 
@@ -49,3 +51,14 @@ pub struct BackupGroup {
     #[serde(default, flatten)]
     pub r#type: Box<dyn BackupType>,
 }
+
+/// Keep only folders tagged for this run: included if `include` is empty
+/// or the folder has any tag in it, and excluded if it has any tag in
+/// `exclude` (checked after inclusion, so exclude always wins).
+pub fn filter_by_tags<T: Folder>(folders: &mut Vec<FolderEntry<T>>, include: &[String], exclude: &[String]) {
+    folders.retain(|f| {
+        let included = include.is_empty() || include.iter().any(|tag| f.tags.contains(tag));
+        let excluded = exclude.iter().any(|tag| f.tags.contains(tag));
+        included && !excluded
+    });
+}
@@ -1,63 +1,192 @@
-use std::process::Child;
-
-use log::{debug, error, info};
-use netstat2::{get_sockets_info, AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo, TcpState};
-
-use crate::utils::cmd::run_cmd_background;
-
-fn is_port_listening(port: u16) -> bool {
-    let af_flags = AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6;
-    let proto_flags = ProtocolFlags::TCP;
-    let sockets_info = get_sockets_info(af_flags, proto_flags);
-    debug!("Checking if port {port} is listening");
-    match sockets_info {
-        Ok(sockets_info) => {
-            let sockets = sockets_info.iter().find(|s| match &s.protocol_socket_info {
-                ProtocolSocketInfo::Tcp(tcp) => {
-                    tcp.state == TcpState::Listen && tcp.local_port == port
-                }
-                _ => false,
-            });
-            sockets.is_some()
-        }
-        Err(_) => false,
+use std::sync::mpsc;
+
+use k8s_openapi::api::{apps::v1::Deployment, core::v1::Pod};
+use kube::{
+    api::{Api, ListParams},
+    Client,
+};
+use log::{error, info};
+use tokio::{net::TcpListener, sync::oneshot};
+
+use super::error::BorgHelperError;
+
+fn proxy_error(deployment: &str, reason: impl ToString) -> BorgHelperError {
+    BorgHelperError::K8sProxyFailed {
+        deployment: deployment.to_string(),
+        reason: reason.to_string(),
     }
 }
 
-pub fn start_k8s_proxy(
+fn is_pod_ready(pod: &Pod) -> bool {
+    pod.status
+        .as_ref()
+        .and_then(|status| status.conditions.as_ref())
+        .map(|conditions| {
+            conditions
+                .iter()
+                .any(|c| c.type_ == "Ready" && c.status == "True")
+        })
+        .unwrap_or(false)
+}
+
+async fn find_ready_pod(
+    client: Client,
     namespace: &str,
-    name: &str,
+    deployment_name: &str,
+) -> Result<String, BorgHelperError> {
+    let deployments: Api<Deployment> = Api::namespaced(client.clone(), namespace);
+    let deployment = deployments
+        .get(deployment_name)
+        .await
+        .map_err(|e| proxy_error(deployment_name, e))?;
+    let labels = deployment
+        .spec
+        .and_then(|spec| spec.selector.match_labels)
+        .ok_or_else(|| proxy_error(deployment_name, "deployment has no label selector"))?;
+    let label_selector = labels
+        .iter()
+        .map(|(k, v)| format!("{k}={v}"))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let pods: Api<Pod> = Api::namespaced(client, namespace);
+    let list = pods
+        .list(&ListParams::default().labels(&label_selector))
+        .await
+        .map_err(|e| proxy_error(deployment_name, e))?;
+
+    list.items
+        .into_iter()
+        .find(is_pod_ready)
+        .and_then(|pod| pod.metadata.name)
+        .ok_or_else(|| proxy_error(deployment_name, "no ready pod found for deployment"))
+}
+
+/// Drives the forward until `stop` fires: accept local connections and
+/// proxy each one to a freshly opened port-forward stream to the pod.
+async fn run_forward(
+    namespace: String,
+    deployment_name: String,
     k8s_port: u16,
     local_port: u16,
-) -> Option<Child> {
-    info!("Starting proxy...");
-    let cmd = format!(
-        "kubectl -n {} port-forward {} {}:{}",
-        namespace, name, k8s_port, local_port
-    );
-    let child = run_cmd_background(&cmd);
-    match child {
-        Ok(mut child) => {
-            // Wait for proxy to run
-            while !is_port_listening(local_port) {
-                // Check if child returned or threw an error. If not -> Program is
-                // still running and we can wait for the port
-                let child_ret = child.try_wait();
-                match child_ret {
-                    Ok(ret) => {
-                        if ret.is_some() {
-                            // Process got killed while we waited for the port to be open
-                            return None;
-                        }
+    ready: mpsc::Sender<Result<(), BorgHelperError>>,
+    mut stop: oneshot::Receiver<()>,
+) {
+    let client = match Client::try_default().await {
+        Ok(client) => client,
+        Err(e) => {
+            let _ = ready.send(Err(proxy_error(&deployment_name, e)));
+            return;
+        }
+    };
+    let pod_name = match find_ready_pod(client.clone(), &namespace, &deployment_name).await {
+        Ok(pod_name) => pod_name,
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+    let pods: Api<Pod> = Api::namespaced(client, &namespace);
+
+    let listener = match TcpListener::bind(("127.0.0.1", local_port)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            let _ = ready.send(Err(proxy_error(&deployment_name, e)));
+            return;
+        }
+    };
+    info!("Forwarding 127.0.0.1:{local_port} -> {pod_name}:{k8s_port}");
+    let _ = ready.send(Ok(()));
+
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            accepted = listener.accept() => {
+                let Ok((mut local, _)) = accepted else { continue };
+                let mut forwarder = match pods.portforward(&pod_name, &[k8s_port]).await {
+                    Ok(forwarder) => forwarder,
+                    Err(e) => {
+                        error!("Failed to open port-forward stream to {pod_name}: {e}");
+                        continue;
                     }
-                    Err(_) => return None,
-                }
+                };
+                let Some(mut upstream) = forwarder.take_stream(k8s_port) else {
+                    continue;
+                };
+                tokio::spawn(async move {
+                    let _ = tokio::io::copy_bidirectional(&mut local, &mut upstream).await;
+                });
             }
-            Some(child)
         }
-        Err(e) => {
-            error!("Failed to call kubectl with error: {}", e);
-            None
+    }
+}
+
+/// Handle to an in-process, kubectl-free port-forward to a deployment's
+/// pod. Dropping it stops the background forwarding task and releases the
+/// local listening socket - no external process to reap.
+pub struct K8sPortForward {
+    stop: Option<oneshot::Sender<()>>,
+    thread: Option<std::thread::JoinHandle<()>>,
+}
+
+impl std::fmt::Debug for K8sPortForward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("K8sPortForward").finish_non_exhaustive()
+    }
+}
+
+impl Drop for K8sPortForward {
+    fn drop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
         }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Resolve `deployment_name` to a ready pod and forward `local_port` to
+/// `k8s_port` on it, entirely in-process via the `kube` API - no `kubectl`
+/// binary, kubeconfig-matching shell environment, or child process involved.
+pub fn start_k8s_proxy(
+    namespace: &str,
+    deployment_name: &str,
+    k8s_port: u16,
+    local_port: u16,
+) -> Result<K8sPortForward, BorgHelperError> {
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let (stop_tx, stop_rx) = oneshot::channel();
+    let namespace = namespace.to_string();
+    let deployment_name_owned = deployment_name.to_string();
+
+    let thread = std::thread::spawn(move || {
+        let runtime = match tokio::runtime::Runtime::new() {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                let _ = ready_tx.send(Err(proxy_error(&deployment_name_owned, e)));
+                return;
+            }
+        };
+        runtime.block_on(run_forward(
+            namespace,
+            deployment_name_owned,
+            k8s_port,
+            local_port,
+            ready_tx,
+            stop_rx,
+        ));
+    });
+
+    match ready_rx.recv() {
+        Ok(Ok(())) => Ok(K8sPortForward {
+            stop: Some(stop_tx),
+            thread: Some(thread),
+        }),
+        Ok(Err(e)) => Err(e),
+        Err(_) => Err(proxy_error(
+            deployment_name,
+            "port-forward task exited before becoming ready",
+        )),
     }
 }
@@ -1,5 +1,7 @@
+use super::error::BorgHelperError;
+
 pub trait Mountable {
-    fn mount(&self) -> bool;
-    fn unmount(&self) -> bool;
+    fn mount(&self) -> Result<(), BorgHelperError>;
+    fn unmount(&self) -> Result<(), BorgHelperError>;
     fn get_mount_path(&self) -> String;
 }
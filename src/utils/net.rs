@@ -0,0 +1,30 @@
+use std::{
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+use super::error::BorgHelperError;
+
+/// Poll `host:port` until a TCP connection succeeds or `timeout` elapses.
+/// Use this after starting a port-forward instead of a fixed sleep - the
+/// forwarded port can come up before the service behind it is actually
+/// accepting connections.
+pub fn wait_for_port(host: &str, port: u16, timeout: Duration) -> Result<(), BorgHelperError> {
+    let addr = format!("{host}:{port}");
+    let deadline = Instant::now() + timeout;
+    loop {
+        let resolved = addr.to_socket_addrs().ok().and_then(|mut a| a.next());
+        if let Some(resolved) = resolved {
+            if TcpStream::connect_timeout(&resolved, Duration::from_millis(200)).is_ok() {
+                return Ok(());
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(BorgHelperError::MountFailed {
+                what: addr,
+                reason: format!("not accepting connections after {}s", timeout.as_secs()),
+            });
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
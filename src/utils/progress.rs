@@ -0,0 +1,195 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+/// One line of borg's `--log-json --progress` output we care about.
+/// `#[serde(default)]` fills in missing fields (an `archive_progress` line
+/// without a path or size still parses); other message types
+/// (`log_message`, ...) are dropped by `observe_line`'s type check.
+#[derive(Deserialize, Debug)]
+struct ArchiveProgressLine {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    path: String,
+    #[serde(default)]
+    original_size: u64,
+}
+
+struct FolderPlan {
+    path: String,
+    planned_bytes: u64,
+    first_seen: Option<Instant>,
+    last_seen: Option<Instant>,
+}
+
+/// Per-folder planned-vs-actual timing, reported once a backup finishes.
+pub struct FolderTiming {
+    pub path: String,
+    pub planned_bytes: u64,
+    pub elapsed: Duration,
+}
+
+/// Tracks planned total bytes (summed from `Folder::get_size` up front)
+/// against what borg reports as processed, fed line-by-line from its
+/// `--log-json --progress` stderr. Mirrors obnam's `backup_progress`/
+/// `accumulated_time`: a running throughput estimate during the run, plus
+/// a final per-folder timing summary.
+pub struct BackupProgress {
+    started: Instant,
+    planned_bytes: u64,
+    folders: Vec<FolderPlan>,
+}
+
+impl BackupProgress {
+    pub fn new(folders: &[(String, u64)]) -> Self {
+        Self {
+            started: Instant::now(),
+            planned_bytes: folders.iter().map(|(_, size)| size).sum(),
+            folders: folders
+                .iter()
+                .map(|(path, size)| FolderPlan {
+                    path: path.clone(),
+                    planned_bytes: *size,
+                    first_seen: None,
+                    last_seen: None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn planned_bytes(&self) -> u64 {
+        self.planned_bytes
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Feed one line of borg's JSON log output. Returns the cumulative
+    /// processed-byte count if the line was an `archive_progress` message.
+    pub fn observe_line(&mut self, line: &str) -> Option<u64> {
+        let parsed: ArchiveProgressLine = serde_json::from_str(line).ok()?;
+        if parsed.kind != "archive_progress" {
+            return None;
+        }
+        let now = Instant::now();
+        if let Some(folder) = self.folders.iter_mut().find(|f| parsed.path.starts_with(&f.path)) {
+            folder.first_seen.get_or_insert(now);
+            folder.last_seen = Some(now);
+        }
+        Some(parsed.original_size)
+    }
+
+    /// Elapsed time between the first and last progress line that
+    /// mentioned a path under each planned folder.
+    pub fn folder_timings(&self) -> Vec<FolderTiming> {
+        self.folders
+            .iter()
+            .map(|f| FolderTiming {
+                path: f.path.clone(),
+                planned_bytes: f.planned_bytes,
+                elapsed: match (f.first_seen, f.last_seen) {
+                    (Some(start), Some(end)) => end.duration_since(start),
+                    _ => Duration::ZERO,
+                },
+            })
+            .collect()
+    }
+}
+
+/// Renders backup progress. Implement this instead of printing straight
+/// from the backup driver to plug in a different UI - a live terminal
+/// bar, a `--json` line-per-update stream for scripting, or nothing.
+pub trait ProgressReporter {
+    fn update(&self, processed_bytes: u64, planned_bytes: u64, elapsed: Duration);
+    fn finish(&self, timings: &[FolderTiming]);
+}
+
+fn human_bytes(bytes: u64) -> String {
+    byte_unit::Byte::from_u64(bytes)
+        .get_appropriate_unit(byte_unit::UnitType::Binary)
+        .to_string()
+}
+
+/// Default `ProgressReporter`: a single overwritten progress line plus a
+/// per-folder summary once the backup finishes.
+pub struct TerminalProgress;
+
+impl ProgressReporter for TerminalProgress {
+    fn update(&self, processed_bytes: u64, planned_bytes: u64, elapsed: Duration) {
+        let percent = if planned_bytes == 0 {
+            0.0
+        } else {
+            processed_bytes as f64 / planned_bytes as f64 * 100.0
+        };
+        let throughput = if elapsed.as_secs_f64() > 0.0 {
+            processed_bytes as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+        print!(
+            "\r{:>5.1}% {} / {} ({}/s, {:.0}s elapsed)    ",
+            percent,
+            human_bytes(processed_bytes),
+            human_bytes(planned_bytes),
+            human_bytes(throughput as u64),
+            elapsed.as_secs_f64(),
+        );
+        let _ = std::io::Write::flush(&mut std::io::stdout());
+    }
+
+    fn finish(&self, timings: &[FolderTiming]) {
+        println!();
+        for timing in timings {
+            println!(
+                "  {} - {} in {:.1}s",
+                timing.path,
+                human_bytes(timing.planned_bytes),
+                timing.elapsed.as_secs_f64()
+            );
+        }
+    }
+}
+
+/// Machine-readable `ProgressReporter`: one JSON object per line on
+/// stdout, so a caller can script against backup progress instead of
+/// scraping the terminal bar.
+pub struct JsonProgress;
+
+impl ProgressReporter for JsonProgress {
+    fn update(&self, processed_bytes: u64, planned_bytes: u64, elapsed: Duration) {
+        println!(
+            "{}",
+            serde_json::json!({
+                "type": "progress",
+                "processed_bytes": processed_bytes,
+                "planned_bytes": planned_bytes,
+                "elapsed_secs": elapsed.as_secs_f64(),
+            })
+        );
+    }
+
+    fn finish(&self, timings: &[FolderTiming]) {
+        for timing in timings {
+            println!(
+                "{}",
+                serde_json::json!({
+                    "type": "folder_summary",
+                    "path": timing.path,
+                    "planned_bytes": timing.planned_bytes,
+                    "elapsed_secs": timing.elapsed.as_secs_f64(),
+                })
+            );
+        }
+    }
+}
+
+/// Pick the `ProgressReporter` implementation for `--json`.
+pub fn reporter_for(json: bool) -> Box<dyn ProgressReporter> {
+    if json {
+        Box::new(JsonProgress)
+    } else {
+        Box::new(TerminalProgress)
+    }
+}
@@ -0,0 +1,78 @@
+use std::process::Child;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
+
+use super::k8s::K8sPortForward;
+
+/// Set once a SIGINT/SIGTERM/SIGHUP has been observed. `backup_create`
+/// checks this between sources and stops starting new work,
+/// but the signal thread itself does not wait for that checkpoint - it
+/// tears down every registered mount/proxy right away.
+pub static SHUTDOWN_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Something a mounted source keeps around that needs tearing down if the
+/// process is killed mid-backup, before `unmount()` gets a chance to run.
+pub trait Cleanup {
+    fn stop(&mut self);
+}
+
+impl Cleanup for Child {
+    fn stop(&mut self) {
+        let _ = self.kill();
+        let _ = self.wait();
+    }
+}
+
+impl Cleanup for K8sPortForward {
+    // Its `Drop` impl already sends the stop signal and joins the
+    // forwarding thread - nothing to do here before it's dropped.
+    fn stop(&mut self) {}
+}
+
+type CleanupHook = Box<dyn Fn() + Send + Sync>;
+
+static CLEANUP_HOOKS: Lazy<Mutex<Vec<CleanupHook>>> = Lazy::new(|| Mutex::new(Vec::new()));
+
+/// Register a mounted source's teardown slot with the cleanup subsystem.
+/// Sources keep their own `Arc<Mutex<Option<T>>>` (a k8s proxy, a child
+/// process, ...) so the normal `unmount()` path and the signal handler
+/// can both take it without racing: whoever gets there first sees
+/// `Some`, the other sees `None` and does nothing.
+pub fn register<T: Cleanup + Send + 'static>(slot: Arc<Mutex<Option<T>>>) {
+    CLEANUP_HOOKS.lock().unwrap().push(Box::new(move || {
+        if let Some(mut item) = slot.lock().unwrap().take() {
+            item.stop();
+        }
+    }));
+}
+
+/// Install the SIGINT/SIGTERM/SIGHUP handler. Must be called once from
+/// `main` before any source is mounted.
+pub fn install() {
+    let mut signals =
+        Signals::new([SIGINT, SIGTERM, SIGHUP]).expect("failed to register signal handler");
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if SHUTDOWN_REQUESTED.swap(true, Ordering::SeqCst) {
+                // A previous signal is already tearing things down.
+                continue;
+            }
+            warn!("Received signal {signal}, cleaning up mounted sources");
+            cleanup();
+            std::process::exit(130);
+        }
+    });
+}
+
+fn cleanup() {
+    let hooks = CLEANUP_HOOKS.lock().unwrap();
+    info!("Tearing down {} tracked mount(s)", hooks.len());
+    for hook in hooks.iter() {
+        hook();
+    }
+}
@@ -0,0 +1,142 @@
+use std::{io::Read, net::TcpStream, path::Path};
+
+use ssh2::{CheckResult, KnownHostFileKind, Session};
+
+use super::error::BorgHelperError;
+
+fn ssh_error(what: impl Into<String>, source: ssh2::Error) -> BorgHelperError {
+    BorgHelperError::MountFailed {
+        what: what.into(),
+        reason: source.to_string(),
+    }
+}
+
+/// Verify `session`'s host key for `host:port` against the user's
+/// `~/.ssh/known_hosts`, the same check the `ssh`/`sshfs` binaries this
+/// session replaces perform by default (`StrictHostKeyChecking`). Callers
+/// that need to skip it for an ephemeral/throwaway host can pass
+/// `insecure_skip_host_key_check`.
+fn verify_host_key(
+    session: &Session,
+    host: &str,
+    port: u16,
+    insecure_skip_host_key_check: bool,
+) -> Result<(), BorgHelperError> {
+    if insecure_skip_host_key_check {
+        return Ok(());
+    }
+    let (key, _key_type) = session
+        .host_key()
+        .ok_or_else(|| BorgHelperError::MountFailed {
+            what: host.to_string(),
+            reason: "server did not present a host key".to_string(),
+        })?;
+
+    let mut known_hosts = session.known_hosts().map_err(|e| ssh_error(host, e))?;
+    if let Some(known_hosts_path) = dirs_home_known_hosts() {
+        // Missing/unreadable known_hosts is treated as "nothing known yet"
+        // below (CheckResult::NotFound), not a hard error.
+        let _ = known_hosts.read_file(&known_hosts_path, KnownHostFileKind::OpenSSH);
+    }
+
+    match known_hosts.check_port(host, port, key) {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(BorgHelperError::MountFailed {
+            what: host.to_string(),
+            reason: "host key not found in known_hosts; add it with ssh-keyscan or pass \
+                     insecure_skip_host_key_check to trust it anyway"
+                .to_string(),
+        }),
+        CheckResult::Mismatch => Err(BorgHelperError::MountFailed {
+            what: host.to_string(),
+            reason: "host key does not match the one in known_hosts (possible MITM)".to_string(),
+        }),
+        CheckResult::Failure => Err(BorgHelperError::MountFailed {
+            what: host.to_string(),
+            reason: "failed to check host key against known_hosts".to_string(),
+        }),
+    }
+}
+
+fn dirs_home_known_hosts() -> Option<std::path::PathBuf> {
+    std::env::var_os("HOME").map(|home| Path::new(&home).join(".ssh").join("known_hosts"))
+}
+
+/// A single, reusable SSH connection plus an authenticated libssh2
+/// session. Exec a command on it via [`SshSession::exec`] instead of
+/// spawning a fresh `ssh` process per call.
+pub struct SshSession {
+    session: Session,
+}
+
+impl std::fmt::Debug for SshSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SshSession").finish_non_exhaustive()
+    }
+}
+
+impl SshSession {
+    /// Connect to `target` (`user@host[:port]`, default port 22) and
+    /// authenticate, trying ssh-agent first, then an explicit `identity`
+    /// file, then falling back to `password` if one is supplied.
+    pub fn connect(
+        target: &str,
+        identity: Option<&Path>,
+        password: Option<&str>,
+        insecure_skip_host_key_check: bool,
+    ) -> Result<Self, BorgHelperError> {
+        let (user, host) = target.split_once('@').unwrap_or(("root", target));
+        let (hostname, port) = host.split_once(':').unwrap_or((host, "22"));
+        let port: u16 = port
+            .parse()
+            .map_err(|_| BorgHelperError::MountFailed { what: target.to_string(), reason: format!("invalid port {port}") })?;
+        let addr = format!("{hostname}:{port}");
+
+        let tcp = TcpStream::connect(&addr)
+            .map_err(|e| BorgHelperError::command_io(format!("ssh connect {addr}"), e))?;
+        let mut session = Session::new().map_err(|e| ssh_error(target, e))?;
+        session.set_tcp_stream(tcp);
+        session.handshake().map_err(|e| ssh_error(target, e))?;
+        verify_host_key(&session, hostname, port, insecure_skip_host_key_check)?;
+
+        if session.userauth_agent(user).is_err() {
+            if let Some(identity) = identity {
+                let _ = session.userauth_pubkey_file(user, None, identity, None);
+            }
+        }
+        if !session.authenticated() {
+            if let Some(password) = password {
+                let _ = session.userauth_password(user, password);
+            }
+        }
+        if !session.authenticated() {
+            return Err(BorgHelperError::MountFailed {
+                what: target.to_string(),
+                reason: "ssh authentication failed (agent, identity file, and password all rejected or unavailable)".to_string(),
+            });
+        }
+
+        Ok(Self { session })
+    }
+
+    /// Run `cmd` over a fresh exec channel on this session, reading stdout
+    /// to EOF, and return `(stdout, exit status)`.
+    pub fn exec(&self, cmd: &str) -> Result<(String, i32), BorgHelperError> {
+        let mut channel = self.session.channel_session().map_err(|e| ssh_error(cmd, e))?;
+        channel.exec(cmd).map_err(|e| ssh_error(cmd, e))?;
+        let mut output = String::new();
+        channel
+            .read_to_string(&mut output)
+            .map_err(|e| BorgHelperError::command_io(cmd, e))?;
+        channel.wait_close().map_err(|e| ssh_error(cmd, e))?;
+        let status = channel.exit_status().map_err(|e| ssh_error(cmd, e))?;
+        Ok((output, status))
+    }
+
+    /// The session's SFTP subsystem, for folder existence/size checks
+    /// without a full sshfs mount.
+    #[allow(dead_code)]
+    pub fn sftp(&self) -> Result<ssh2::Sftp, BorgHelperError> {
+        self.session.sftp().map_err(|e| ssh_error("sftp", e))
+    }
+}